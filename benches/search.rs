@@ -0,0 +1,100 @@
+//! Compares exploring the game tree by cloning every child against making and unmaking moves
+//! on a single state, in nodes per second on a 4x8x4 playfield.
+
+use std::time::{Duration, Instant};
+
+use queue::Queue;
+use ternix::{
+    brute_forcing::{self, SearchConfig},
+    tuning, Action, Evaluator, GameOutcome, GameState, Weights,
+};
+
+type State = GameState<4, 8, 4>;
+
+const DEPTH: usize = 2;
+const PIECES: usize = 40;
+
+/// Visits every state up to `depth` placements deep, the way the search did before it made
+/// and unmade moves: by cloning the state for every placement, performing its actions on the
+/// clone and scoring that.
+fn cloning(game_state: State, depth: usize) -> usize {
+    if depth == 0 || !game_state.outcome().is_running() {
+        return 1;
+    }
+
+    let mut held = game_state.clone();
+    let roots = if held.perform(Action::Hold, true) {
+        vec![game_state, held]
+    } else {
+        vec![game_state]
+    };
+
+    let mut children = Vec::new();
+    for root in roots {
+        for placement in root.placements() {
+            let mut child = root.clone();
+            for action in placement.actions {
+                child.perform(action, true);
+            }
+            if child.outcome() != GameOutcome::ToppedOut {
+                children.push((Weights::default().evaluate(&child), child));
+            }
+        }
+    }
+    children.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+
+    1 + children
+        .into_iter()
+        .map(|(_, child)| cloning(child, depth - 1))
+        .sum::<usize>()
+}
+
+/// Visits the same states as [`cloning`], on a single state.
+fn making(game_state: &mut State, depth: usize) -> usize {
+    if depth == 0 || !game_state.outcome().is_running() {
+        return 1;
+    }
+
+    let mut nodes = 1;
+    for (_, actions) in brute_forcing::scored_moves(game_state, &Weights::default()) {
+        game_state.make(&actions);
+        nodes += making(game_state, depth - 1);
+        game_state.unmake(&actions);
+    }
+    nodes
+}
+
+fn report(name: &str, nodes: usize, elapsed: Duration) {
+    println!(
+        "{name:>12}: {nodes} nodes in {elapsed:.2?}, {:.0} nodes/s",
+        nodes as f64 / elapsed.as_secs_f64()
+    );
+}
+
+fn main() {
+    // a game some way in, whose history cloning a state copies along
+    let game_state = tuning::play(
+        State::from(Queue::default().with_seed(0)),
+        &Weights::default(),
+        PIECES,
+    );
+
+    // the search kept no actions to undo before it made and unmade moves
+    let start = Instant::now();
+    let nodes = cloning(game_state.clone().with_undo_limit(0), DEPTH);
+    report("cloning", nodes, start.elapsed());
+
+    let start = Instant::now();
+    let nodes = making(&mut game_state.clone(), DEPTH);
+    report("make/unmake", nodes, start.elapsed());
+
+    let config = SearchConfig {
+        max_depth: DEPTH + 1,
+        max_nodes: Some(100_000),
+        iterative_deepening: false,
+        ..Default::default()
+    };
+    let start = Instant::now();
+    let result = brute_forcing::algorithm(game_state, &config);
+    report("search", result.nodes, start.elapsed());
+}
@@ -1,3 +1,5 @@
+use std::fmt;
+
 use colored::Colorize;
 use strum::{EnumCount, IntoEnumIterator};
 use tetrominos::Tetromino;
@@ -36,8 +38,8 @@ impl<const W: usize, const D: usize> Layer<W, D> {
     }
 }
 
-impl<const W: usize, const D: usize> ToString for Layer<W, D> {
-    fn to_string(&self) -> String {
+impl<const W: usize, const D: usize> fmt::Display for Layer<W, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut output = String::from(" ");
 
         for z in (0..D).rev() {
@@ -60,6 +62,6 @@ impl<const W: usize, const D: usize> ToString for Layer<W, D> {
             output.push_str("\n ");
         }
 
-        output
+        write!(f, "{output}")
     }
 }
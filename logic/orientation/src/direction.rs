@@ -55,9 +55,9 @@ impl From<(Polarity, Axis)> for Direction {
     }
 }
 
-impl Into<(Polarity, Axis)> for Direction {
-    fn into(self) -> (Polarity, Axis) {
-        (self.polarity, self.axis)
+impl From<Direction> for (Polarity, Axis) {
+    fn from(direction: Direction) -> Self {
+        (direction.polarity, direction.axis)
     }
 }
 
@@ -1,9 +1,9 @@
 use orientation::{Axis, Direction};
-use shapes::{Bitfield, Shape, ShapeId};
+use shapes::{Bitfield, Canonical, Shape, ShapeId};
 use strum::EnumCount;
 use tetrominos::Tetromino;
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub struct Piece {
     pub shape_id: ShapeId,
     pub position: [isize; Axis::COUNT],
@@ -18,6 +18,17 @@ impl Piece {
         (&self.shape_id).into()
     }
 
+    /// The piece in the canonical orientation that covers the same cells.
+    pub fn canonical(&self) -> Self {
+        let Canonical { shape_id, offset } = self.shape_id.canonical();
+        let mut piece = Self {
+            shape_id,
+            position: self.position,
+        };
+        piece.translate(offset);
+        piece
+    }
+
     pub fn translate(&mut self, offset: [isize; Axis::COUNT]) {
         let [x, y, z] = self.position;
         let [dx, dy, dz] = offset;
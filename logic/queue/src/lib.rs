@@ -1,73 +1,657 @@
-use regex::Regex;
-use std::{collections::VecDeque, str::FromStr};
+use rand::SeedableRng;
+use std::{collections::VecDeque, fmt, str::FromStr};
 use tetrominos::Tetromino;
 
 mod parsing;
 mod pattern;
+mod randomizer;
+mod rng;
+mod sequence;
 
+use parsing::Parser;
 pub use parsing::Parsing;
 pub use pattern::Pattern;
+pub use randomizer::{
+    Bag, Cyclic, History, Patterns, Randomizer, RandomizerMode, SavedMode, Uniform,
+};
+pub use rng::Pcg32;
+pub use sequence::Sequence;
+pub use tetrominos::ParseError;
 
-#[derive(Debug, Default, Clone, Hash, PartialEq, Eq)]
-pub struct Queue {
-    pub sequence: VecDeque<Pattern>,
+/// Yields tetrominos through a [`Randomizer`], which is one of the [`RandomizerMode`]s unless
+/// another one is plugged in with [`Queue::with_randomizer`].
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct Queue<R = RandomizerMode> {
+    pub randomizer: R,
     pub hold: Option<Tetromino>,
     pub can_swap: bool,
+    /// Decides every random draw, so a clone of the queue yields the same tetrominos.
+    pub rng: Pcg32,
+    /// The next tetrominos, drawn ahead of time.
+    pub preview: VecDeque<Tetromino>,
+    /// Number of tetrominos the preview is kept filled with, as far as the queue reaches.
+    pub preview_length: usize,
 }
 
 impl Queue {
-    pub fn next(&mut self) -> Tetromino {
-        let mut pattern = self.sequence.pop_front().unwrap_or_default();
-        if let Some(variant) = pattern.draw() {
-            self.sequence.push_front(pattern);
-            return variant;
+    /// The patterns left to draw from, unless another randomizer was selected.
+    pub fn patterns(&self) -> Option<&Patterns> {
+        match &self.randomizer {
+            RandomizerMode::Patterns(patterns) => Some(patterns),
+            _ => None,
         }
-        self.next()
     }
 }
 
-impl Parsing for Queue {
-    fn parse<T: Into<String>>(value: T) -> Result<Self, String> {
-        let input = value.into();
-
-        let hold_pattern = r"^((?<hold>.):)?";
-        let sequence_pattern = r"(?<pattern>((\[.+?\])|\*)(p\d+)?)";
-
-        let hold = Regex::new(format!("{hold_pattern}{sequence_pattern}").as_str())
-            .map_err(|_| "Invalid regex pattern!")?
-            .captures(input.as_str())
-            .ok_or("Invalid queue format!")?
-            .name("hold")
-            .map(|capture| Tetromino::parse(&capture.as_str()[..1]))
-            .transpose()
-            .map_err(|err| err + " at hold!")?;
-
-        let mut sequence = VecDeque::new();
-        for (i, captures) in Regex::new(&sequence_pattern)
-            .map_err(|_| "Invalid regex pattern!")?
-            .captures_iter(input.as_str())
-            .enumerate()
-        {
-            if let Some(capture) = captures.name("pattern") {
-                sequence.push_back(
-                    Pattern::parse(capture.as_str())
-                        .map_err(|err| format!("{err} at pattern {}!", i + 1))?,
-                );
+impl<R: Randomizer> Queue<R> {
+    /// Reseeds the queue, so that the same queue and seed always yield the same tetrominos.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = Pcg32::seed_from_u64(seed);
+        self
+    }
+
+    /// Draws the tetrominos with `randomizer` from now on, discarding the preview.
+    pub fn with_randomizer<S: Randomizer>(self, randomizer: S) -> Queue<S> {
+        let mut queue = Queue {
+            randomizer,
+            hold: self.hold,
+            can_swap: self.can_swap,
+            rng: self.rng,
+            preview: VecDeque::new(),
+            preview_length: self.preview_length,
+        };
+        queue.fill_preview();
+        queue
+    }
+
+    /// Draws the next `length` tetrominos ahead of time, so that they can be seen with
+    /// [`Queue::peek`]. The tetrominos beyond the preview stay random.
+    pub fn with_preview(mut self, length: usize) -> Self {
+        self.preview_length = length;
+        self.fill_preview();
+        self
+    }
+
+    /// Up to `n` of the next tetrominos, as far as the preview reaches.
+    pub fn peek(&self, n: usize) -> Vec<Tetromino> {
+        self.preview.iter().take(n).copied().collect()
+    }
+
+    /// Draws the next tetromino.
+    ///
+    /// # Panics
+    ///
+    /// Panics once a finite queue has run out, which [`Queue::try_next`] reports instead.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Tetromino {
+        self.try_next().expect("the queue has run out")
+    }
+
+    fn fill_preview(&mut self) {
+        while self.preview.len() < self.preview_length {
+            let Some(variant) = self.randomizer.draw(&mut self.rng) else {
+                break;
+            };
+            self.preview.push_back(variant);
+        }
+    }
+
+    /// Like [`Queue::next`], but returns `None` once a finite queue has run out.
+    pub fn try_next(&mut self) -> Option<Tetromino> {
+        let variant = match self.preview.pop_front() {
+            Some(variant) => variant,
+            None => self.randomizer.draw(&mut self.rng)?,
+        };
+        self.fill_preview();
+        Some(variant)
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.preview.is_empty() && self.randomizer.remaining() == Some(0)
+    }
+
+    /// Number of tetrominos left to draw, or `None` if the queue is endless.
+    pub fn remaining(&self) -> Option<usize> {
+        self.randomizer
+            .remaining()
+            .map(|remaining| self.preview.len() + remaining)
+    }
+
+    /// Every tetromino the next draw could yield.
+    pub fn candidates(&self) -> Vec<Tetromino> {
+        if let Some(&variant) = self.preview.front() {
+            return vec![variant];
+        }
+
+        self.randomizer.candidates()
+    }
+
+    /// Draws `variant` instead of a random tetromino, if the queue can yield it next.
+    pub fn take(&mut self, variant: Tetromino) -> bool {
+        if let Some(&next) = self.preview.front() {
+            if next != variant {
+                return false;
             }
+            self.preview.pop_front();
+            self.fill_preview();
+            return true;
+        }
+
+        self.randomizer.take(variant)
+    }
+
+    /// Makes `variant` the next tetromino to draw, if the queue can yield it next, which leaves
+    /// the queue as drawing it would have after the draw.
+    pub fn force(&mut self, variant: Tetromino) -> bool {
+        if !self.take(variant) {
+            return false;
         }
+        self.preview.push_front(variant);
+        true
+    }
 
-        Ok(Self {
-            sequence,
+    /// Puts `variant` into the hold slot and returns the tetromino that replaces it,
+    /// which is either the previously held one or the next one in the sequence.
+    pub fn swap(&mut self, variant: Tetromino) -> Option<Tetromino> {
+        if !self.can_swap {
+            return None;
+        }
+
+        let replacement = match self.hold {
+            Some(held) => held,
+            None => self.try_next()?,
+        };
+        self.hold = Some(variant);
+        self.can_swap = false;
+
+        Some(replacement)
+    }
+
+    /// Saves what the next draw, take or swap changes, without copying the rest of the queue.
+    pub fn checkpoint(&self) -> Checkpoint<R::Saved> {
+        Checkpoint {
+            randomizer: self.randomizer.save(),
+            rng: self.rng.clone(),
+            hold: self.hold,
+            can_swap: self.can_swap,
+            preview: self.preview.clone(),
+        }
+    }
+
+    /// Takes back the draw, take or swap made since `checkpoint` was saved.
+    pub fn restore(&mut self, checkpoint: Checkpoint<R::Saved>) {
+        let Checkpoint {
+            randomizer,
+            rng,
             hold,
+            can_swap,
+            preview,
+        } = checkpoint;
+
+        self.randomizer.restore(randomizer);
+        self.rng = rng;
+        self.hold = hold;
+        self.can_swap = can_swap;
+        self.preview = preview;
+    }
+}
+
+/// The part of a queue that drawing, taking or swapping a single tetromino changes, saved by
+/// [`Queue::checkpoint`]. Only a preview makes it allocate.
+#[derive(Debug, Clone)]
+pub struct Checkpoint<S> {
+    randomizer: S,
+    rng: Pcg32,
+    hold: Option<Tetromino>,
+    can_swap: bool,
+    preview: VecDeque<Tetromino>,
+}
+
+/// An endless queue of full bags. Every queue starts from the same seed, so that parsing or
+/// building the same queue twice yields the same tetrominos; [`Queue::with_seed`] varies them.
+impl Default for Queue {
+    fn default() -> Self {
+        Self {
+            randomizer: RandomizerMode::default(),
+            hold: None,
             can_swap: true,
-        })
+            rng: Pcg32::seed_from_u64(0),
+            preview: VecDeque::new(),
+            preview_length: 0,
+        }
+    }
+}
+
+/// Writes the queue in the syntax [`Queue::parse`] reads, with the preview as fixed tetrominos.
+/// It leaves out the state of the random number generator, the length of the preview, whether
+/// the piece can be swapped and any randomizer but a finite sequence of patterns, so parsing
+/// the text only yields an equal queue if those are as [`Queue::parse`] sets them: the default
+/// seed, no preview and a piece that can be swapped. Otherwise it yields a queue that can draw
+/// the same tetrominos, with the same chances. A queue with neither a hold nor anything left to
+/// draw writes nothing, which is not a queue to parse.
+impl fmt::Display for Queue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(hold) = self.hold {
+            write!(f, "{hold:?}:")?;
+        }
+        for variant in self.preview.iter() {
+            write!(f, "{variant:?}")?;
+        }
+        for pattern in self
+            .patterns()
+            .iter()
+            .flat_map(|patterns| patterns.sequence.iter())
+        {
+            write!(f, "{pattern}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Parsed queues are finite: once their patterns run out they yield nothing more, so a game
+/// drawing from one ends with `QueueExhausted`. Only [`Queue::default`] and patterns marked
+/// [`Patterns::endless`] go on drawing full bags.
+impl FromStr for Queue {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Parser::new(s).queue()
+    }
+}
+
+impl Parsing for Queue {
+    fn parse<T: Into<String>>(value: T) -> Result<Self, ParseError> {
+        value.into().parse()
     }
 }
 
 impl Parsing for Tetromino {
-    fn parse<T: Into<String>>(input: T) -> Result<Self, String> {
-        let input = input.into();
-        Tetromino::from_str(input.as_str())
-            .map_err(|_| format!("Invalid tetromino character '{}'", input))
+    fn parse<T: Into<String>>(input: T) -> Result<Self, ParseError> {
+        input.into().parse()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draw(queue: &mut Queue) -> Vec<Tetromino> {
+        std::iter::from_fn(|| queue.try_next()).collect()
+    }
+
+    #[test]
+    fn seeded() {
+        let queue = Queue::parse("[IOTLSBDF]p8*p4").unwrap();
+
+        let mut first = queue.clone().with_seed(7);
+        let mut second = queue.with_seed(7);
+        assert_eq!(draw(&mut first), draw(&mut second));
+
+        // without a seed of their own, equal queues draw the same tetrominos
+        let mut first = Queue::parse("*p7").unwrap();
+        let mut second = Queue::parse("*p7").unwrap();
+        assert_eq!(first, second);
+        assert_eq!(draw(&mut first), draw(&mut second));
+
+        let mut endless = Queue::default().with_seed(3);
+        endless.next();
+        let mut clone = endless.clone();
+        let (a, b): (Vec<_>, Vec<_>) = (0..20).map(|_| (endless.next(), clone.next())).unzip();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn sequences() {
+        use Tetromino::*;
+
+        let sequences = Queue::parse("[IIO]p2[T]p1").unwrap().sequences();
+        let orders: Vec<_> = sequences
+            .iter()
+            .map(|sequence| (sequence.tetrominos.clone(), sequence.count))
+            .collect();
+        assert_eq!(
+            orders,
+            [(vec![I, I, T], 2), (vec![I, O, T], 2), (vec![O, I, T], 2)]
+        );
+        assert!(sequences
+            .iter()
+            .all(|sequence| (sequence.probability - 1.0 / 3.0).abs() < 1e-12));
+
+        let sequences = Queue::parse("*p7").unwrap().sequences();
+        assert_eq!(sequences.len(), 40320);
+        let total: f64 = sequences.iter().map(|sequence| sequence.probability).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+
+        let mut fixed = Queue::fixed(sequences[1234].tetrominos.clone());
+        assert_eq!(draw(&mut fixed), sequences[1234].tetrominos);
+    }
+
+    #[test]
+    fn grammar() {
+        use Tetromino::*;
+
+        let queue = Queue::parse("T:IO[^IO]p2*!*p8[SS]r3").unwrap();
+        let sequence = &queue.patterns().unwrap().sequence;
+        assert_eq!(queue.hold, Some(T));
+        assert_eq!(sequence[0], Pattern::single(I));
+        assert_eq!(sequence[1], Pattern::single(O));
+        assert_eq!(sequence[2].tetromino_set, [0, 0, 1, 1, 1, 1, 1, 1]);
+        assert_eq!(sequence[2].draw_count, 2);
+        assert_eq!(sequence[3], sequence[4]);
+        assert_eq!(sequence[3], Pattern::default());
+        assert_eq!(sequence[5].draw_count, 3);
+        assert_eq!(queue.remaining(), Some(2 + 2 + 8 + 8 + 3));
+
+        assert_eq!(Queue::parse("[IOTL]p3[*]p4"), Queue::parse("[IOTL]p3*p4"));
+        let doubled = Pattern::parse("[*I]").unwrap().tetromino_set;
+        assert_eq!(doubled, [2, 1, 1, 1, 1, 1, 1, 1]);
+        assert!(Queue::parse("[^*]").is_err());
+
+        // parsed queues end with their patterns, unless they are made endless
+        let mut finite = Queue::parse("I").unwrap();
+        assert_eq!(finite.remaining(), Some(1));
+        assert_eq!(draw(&mut finite), [I]);
+        let RandomizerMode::Patterns(patterns) = &mut finite.randomizer else {
+            unreachable!()
+        };
+        patterns.endless = true;
+        assert_eq!(finite.remaining(), None);
+        assert!(finite.try_next().is_some());
+        assert_eq!(Queue::default().remaining(), None);
+
+        let mut replaced = Queue::parse("[S]r3").unwrap();
+        assert_eq!(draw(&mut replaced), [S, S, S]);
+        assert_eq!(Queue::parse("[IO]r2").unwrap().sequences().len(), 4);
+
+        assert_eq!(
+            Queue::parse("[IO]p2[IX]").unwrap_err(),
+            ParseError::UnknownTetromino {
+                character: 'X',
+                span: 8..9
+            }
+        );
+        assert_eq!(
+            "*p9".parse::<Queue>().unwrap_err(),
+            ParseError::CountTooLarge {
+                count: 9,
+                size: 8,
+                span: 2..3
+            }
+        );
+        assert_eq!(
+            Queue::parse("[IO").unwrap_err(),
+            ParseError::UnclosedSet { span: 0..3 }
+        );
+        assert_eq!(
+            Queue::parse("IO:T").unwrap_err(),
+            ParseError::MalformedHold { span: 0..3 }
+        );
+        assert_eq!(
+            Queue::parse("*p").unwrap_err(),
+            ParseError::BadCount { span: 2..2 }
+        );
+        assert_eq!(
+            Queue::parse("*px").unwrap_err().to_string(),
+            "Invalid count at bytes 2..2"
+        );
+        assert_eq!(
+            Queue::parse("").unwrap_err(),
+            ParseError::EmptyInput { span: 0..0 }
+        );
+        assert_eq!("T".parse::<Tetromino>(), Ok(T));
+        assert_eq!(
+            "X".parse::<Tetromino>(),
+            Err(ParseError::UnknownTetromino {
+                character: 'X',
+                span: 0..1
+            })
+        );
+        assert_eq!(
+            Tetromino::parse("IO"),
+            Err(ParseError::TrailingInput { span: 1..2 })
+        );
+        assert_eq!("[IO]p2".parse(), Pattern::parse("[IO]p2"));
+        assert!(Pattern::parse("*p2I").is_err());
+    }
+
+    #[test]
+    fn display_round_trip() {
+        use rand::Rng;
+        use strum::EnumCount;
+
+        let mut rng = Pcg32::seed_from_u64(18);
+        for _ in 0..500 {
+            let mut queue = Queue {
+                hold: rng
+                    .gen_bool(0.5)
+                    .then(|| Tetromino::from(rng.gen_range(0..Tetromino::COUNT))),
+                randomizer: RandomizerMode::Patterns(Patterns::default()),
+                ..Default::default()
+            };
+            let RandomizerMode::Patterns(patterns) = &mut queue.randomizer else {
+                unreachable!()
+            };
+            for _ in 0..rng.gen_range(0..6) {
+                let tetromino_set = std::array::from_fn(|_| rng.gen_range(0..3));
+                let size: usize = tetromino_set.iter().sum();
+                let replacement = size > 0 && rng.gen_bool(0.3);
+                let draw_count = match (size, replacement) {
+                    (0, _) => 0,
+                    (_, true) => rng.gen_range(0..10),
+                    (_, false) => rng.gen_range(0..=size),
+                };
+                patterns.sequence.push_back(Pattern {
+                    tetromino_set,
+                    draw_count,
+                    replacement,
+                });
+            }
+            let text = queue.to_string();
+            let Ok(parsed) = Queue::parse(text.as_str()) else {
+                // a queue without a hold or patterns writes nothing, which does not parse
+                assert!(text.is_empty());
+                continue;
+            };
+            assert_eq!(parsed, queue, "{text}");
+
+            // once the queue has drawn, the text keeps what it can yield but not how it got there
+            queue = queue.with_preview(rng.gen_range(0..3));
+            for _ in 0..rng.gen_range(0..8) {
+                queue.try_next();
+            }
+            queue.can_swap = rng.gen_bool(0.5);
+
+            let text = queue.to_string();
+            let Ok(parsed) = Queue::parse(text.as_str()) else {
+                assert!(text.is_empty());
+                continue;
+            };
+            let fixed = queue
+                .preview
+                .iter()
+                .map(|&variant| Pattern::single(variant));
+            let expected = Queue {
+                randomizer: RandomizerMode::Patterns(Patterns {
+                    sequence: fixed
+                        .chain(queue.patterns().unwrap().sequence.iter().cloned())
+                        .collect(),
+                    endless: false,
+                }),
+                hold: queue.hold,
+                ..Default::default()
+            };
+            assert_eq!(parsed, expected, "{text}");
+        }
+
+        // other randomizers only show their preview
+        let cyclic = Queue::default()
+            .with_randomizer(RandomizerMode::Cyclic(Cyclic::new(vec![
+                Tetromino::I,
+                Tetromino::O,
+            ])))
+            .with_preview(3);
+        assert_eq!(cyclic.to_string(), "IOI");
+        assert_eq!(Queue::parse("IOI").unwrap().to_string(), "IOI");
+
+        assert_eq!(
+            Queue::parse("T:I[IO]*![^IO]p2[SS]r3").unwrap().to_string(),
+            "T:I[IO]*![TLSBDF]p2[SS]r3"
+        );
+    }
+
+    #[test]
+    fn preview() {
+        use Tetromino::*;
+
+        let mut queue = Queue::parse("[IOT]!")
+            .unwrap()
+            .with_seed(19)
+            .with_preview(2);
+        let preview = queue.peek(5);
+        assert_eq!(preview.len(), 2);
+        assert_eq!(queue.candidates(), [preview[0]]);
+        assert_eq!(queue.remaining(), Some(3));
+
+        let last = queue.patterns().unwrap().sequence[0].candidates()[0];
+        assert!(!queue.take(last));
+        let mut forced = queue.clone();
+        assert!(!forced.force(last) && forced.force(preview[0]));
+        assert_eq!(forced.next(), preview[0]);
+        assert_eq!(queue.next(), preview[0]);
+        assert_eq!(forced, queue);
+        assert_eq!(queue.peek(1), [preview[1]]);
+        assert_eq!(queue.peek(2).len(), 2);
+
+        let rest: Vec<_> = draw(&mut queue);
+        let mut drawn = vec![preview[0]];
+        drawn.extend(rest);
+        drawn.sort_by_key(|&variant| variant as usize);
+        assert_eq!(drawn, [I, O, T]);
+    }
+
+    #[test]
+    fn checkpoints() {
+        use Tetromino::*;
+
+        let queues = [
+            Queue::parse("T:[IO]p1[IOT]!*p3").unwrap(),
+            Queue::parse("[SS]r2").unwrap().with_preview(1),
+            Queue::default().with_seed(21),
+            Queue::default().with_randomizer(RandomizerMode::Uniform(Uniform)),
+            Queue::default().with_randomizer(RandomizerMode::History(History::new(7))),
+            Queue::default().with_randomizer(RandomizerMode::Cyclic(Cyclic::new(vec![I, O]))),
+        ];
+        for mut queue in queues {
+            for _ in 0..12 {
+                let before = queue.clone();
+
+                let checkpoint = queue.checkpoint();
+                queue.swap(F);
+                queue.restore(checkpoint);
+                assert_eq!(queue, before);
+
+                if let Some(&variant) = queue.candidates().last() {
+                    let checkpoint = queue.checkpoint();
+                    assert!(queue.take(variant));
+                    queue.restore(checkpoint);
+                    assert_eq!(queue, before);
+
+                    let checkpoint = queue.checkpoint();
+                    assert!(queue.force(variant));
+                    assert_eq!(queue.next(), variant);
+                    queue.restore(checkpoint);
+                    assert_eq!(queue, before);
+                }
+
+                let checkpoint = queue.checkpoint();
+                let drawn = queue.try_next();
+                let after = queue.clone();
+                queue.restore(checkpoint);
+                assert_eq!(queue, before);
+
+                queue = after;
+                if drawn.is_none() {
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn randomizers() {
+        use Tetromino::*;
+
+        let mut bag = Queue::default().with_seed(20);
+        for _ in 0..3 {
+            let mut drawn: Vec<_> = (0..8).map(|_| bag.next()).collect();
+            drawn.sort_by_key(|&variant| variant as usize);
+            assert_eq!(drawn, [I, O, T, L, S, B, D, F]);
+        }
+
+        let mut history = Queue::default()
+            .with_seed(20)
+            .with_randomizer(RandomizerMode::History(History::new(3)));
+        let drawn: Vec<_> = (0..200).map(|_| history.next()).collect();
+        assert!(drawn
+            .windows(4)
+            .all(|window| (1..4).all(|i| !window[..i].contains(&window[i]))));
+
+        let mut uniform = Queue::default()
+            .with_seed(20)
+            .with_randomizer(RandomizerMode::Uniform(Uniform));
+        assert_eq!(uniform.candidates().len(), 8);
+        assert_eq!(uniform.remaining(), None);
+        assert!(uniform.take(I) && uniform.take(I));
+
+        let mut cyclic = Queue::default()
+            .with_randomizer(RandomizerMode::Cyclic(Cyclic::new(vec![I, O, T])))
+            .with_preview(2);
+        assert_eq!(cyclic.peek(2), [I, O]);
+        assert!(!cyclic.take(O));
+        let drawn: Vec<_> = (0..7).map(|_| cyclic.next()).collect();
+        assert_eq!(drawn, [I, O, T, I, O, T, I]);
+
+        // a finite queue runs out instead of falling back to full bags
+        let mut finite = Queue::parse("IO").unwrap().with_preview(3);
+        assert_eq!(draw(&mut finite), [I, O]);
+        assert!(finite.is_exhausted());
+        assert_eq!(finite.try_next(), None);
+
+        // any randomizer can be plugged in
+        struct Alternating(bool);
+        impl Randomizer for Alternating {
+            fn draw(&mut self, _: &mut dyn rand::RngCore) -> Option<Tetromino> {
+                self.0 = !self.0;
+                Some(if self.0 { S } else { B })
+            }
+            fn candidates(&self) -> Vec<Tetromino> {
+                vec![if self.0 { B } else { S }]
+            }
+            fn take(&mut self, variant: Tetromino) -> bool {
+                if self.candidates() != [variant] {
+                    return false;
+                }
+                self.0 = !self.0;
+                true
+            }
+            fn remaining(&self) -> Option<usize> {
+                None
+            }
+            type Saved = bool;
+            fn save(&self) -> bool {
+                self.0
+            }
+            fn restore(&mut self, saved: bool) {
+                self.0 = saved;
+            }
+        }
+        let mut alternating = Queue::parse("T:")
+            .unwrap()
+            .with_randomizer(Alternating(false))
+            .with_preview(1);
+        assert_eq!(alternating.peek(1), [S]);
+        assert_eq!(alternating.swap(I), Some(T));
+        let drawn: Vec<_> = (0..3).map(|_| alternating.next()).collect();
+        assert_eq!(drawn, [S, B, S]);
     }
 }
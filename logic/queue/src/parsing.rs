@@ -1,5 +1,202 @@
+use std::{collections::VecDeque, iter::Peekable, ops::Range, str::CharIndices};
+
+use strum::{EnumCount, IntoEnumIterator};
+use tetrominos::Tetromino;
+
+use crate::{ParseError, Pattern, Patterns, Queue, RandomizerMode};
+
 pub trait Parsing {
-    fn parse<T: Into<String>>(input: T) -> Result<Self, String>
+    fn parse<T: Into<String>>(input: T) -> Result<Self, ParseError>
     where
         Self: Sized;
 }
+
+/// A hand-written parser for the queue grammar:
+///
+/// ```text
+/// queue   := (tetromino ':')? pattern*
+/// pattern := tetromino | set suffix?
+/// set     := '*' | '[' '^'? (tetromino | '*')+ ']'
+/// suffix  := 'p' count | 'r' count | '!'
+/// ```
+///
+/// A bare tetromino is drawn as is, `pN` draws N tetrominos from the set without replacement,
+/// `rN` draws N with replacement and `!` draws the whole set. A set without a suffix draws one.
+/// Inside brackets, `*` adds one of every tetromino, so `[*]p4` is the same as `*p4`.
+pub(crate) struct Parser<'a> {
+    input: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self {
+            input,
+            chars: input.char_indices().peekable(),
+        }
+    }
+
+    pub fn queue(mut self) -> Result<Queue, ParseError> {
+        if self.input.is_empty() {
+            return Err(ParseError::EmptyInput { span: 0..0 });
+        }
+
+        let mut hold = None;
+        if let Some(colon) = self.input.find(':') {
+            let first = self.input.chars().next().map_or(0, char::len_utf8);
+            if colon != first {
+                return Err(ParseError::MalformedHold { span: 0..colon + 1 });
+            }
+            hold = Some(self.tetromino()?);
+            self.chars.next();
+        }
+
+        let mut sequence = VecDeque::new();
+        while self.chars.peek().is_some() {
+            sequence.push_back(self.pattern()?);
+        }
+
+        Ok(Queue {
+            randomizer: RandomizerMode::Patterns(Patterns {
+                sequence,
+                endless: false,
+            }),
+            hold,
+            ..Default::default()
+        })
+    }
+
+    /// Parses an input consisting of exactly one pattern.
+    pub fn single_pattern(mut self) -> Result<Pattern, ParseError> {
+        let pattern = self.pattern()?;
+        match self.chars.next() {
+            Some((index, _)) => Err(ParseError::TrailingInput {
+                span: index..self.input.len(),
+            }),
+            None => Ok(pattern),
+        }
+    }
+
+    fn pattern(&mut self) -> Result<Pattern, ParseError> {
+        let (tetromino_set, start) = match self.chars.peek().copied() {
+            Some((start, '*')) => {
+                self.chars.next();
+                ([1; Tetromino::COUNT], start)
+            }
+            Some((start, '[')) => (self.set()?, start),
+            Some(_) => return Ok(Pattern::single(self.tetromino()?)),
+            None => {
+                return Err(ParseError::MissingPattern {
+                    span: self.input.len()..self.input.len(),
+                })
+            }
+        };
+        let size: usize = tetromino_set.iter().sum();
+
+        let mut pattern = Pattern {
+            tetromino_set,
+            draw_count: 1,
+            replacement: false,
+        };
+        match self.chars.peek().copied() {
+            Some((_, '!')) => {
+                self.chars.next();
+                pattern.draw_count = size;
+            }
+            Some((_, 'p')) => {
+                self.chars.next();
+                let (span, count) = self.count()?;
+                if count > size {
+                    return Err(ParseError::CountTooLarge { count, size, span });
+                }
+                pattern.draw_count = count;
+            }
+            Some((_, 'r')) => {
+                self.chars.next();
+                pattern.draw_count = self.count()?.1;
+                pattern.replacement = true;
+            }
+            _ => {}
+        }
+
+        if size == 0 && pattern.draw_count > 0 {
+            return Err(ParseError::EmptySet {
+                span: start..self.position(),
+            });
+        }
+        Ok(pattern)
+    }
+
+    fn set(&mut self) -> Result<[usize; Tetromino::COUNT], ParseError> {
+        let (start, _) = self.chars.next().expect("a set starts with '['");
+        let negated = self
+            .chars
+            .next_if(|&(_, character)| character == '^')
+            .is_some();
+
+        let mut tetromino_set = [0; Tetromino::COUNT];
+        loop {
+            match self.chars.peek().copied() {
+                Some((_, ']')) => {
+                    self.chars.next();
+                    break;
+                }
+                Some((_, '*')) => {
+                    self.chars.next();
+                    for count in tetromino_set.iter_mut() {
+                        *count += 1;
+                    }
+                }
+                Some(_) => tetromino_set[self.tetromino()? as usize] += 1,
+                None => {
+                    return Err(ParseError::UnclosedSet {
+                        span: start..self.input.len(),
+                    })
+                }
+            }
+        }
+
+        if negated {
+            for variant in Tetromino::iter() {
+                let excluded = tetromino_set[variant as usize] > 0;
+                tetromino_set[variant as usize] = usize::from(!excluded);
+            }
+        }
+        Ok(tetromino_set)
+    }
+
+    fn tetromino(&mut self) -> Result<Tetromino, ParseError> {
+        let Some((index, character)) = self.chars.next() else {
+            return Err(ParseError::MissingPattern {
+                span: self.input.len()..self.input.len(),
+            });
+        };
+        Tetromino::try_from(character).map_err(|_| ParseError::UnknownTetromino {
+            character,
+            span: index..index + character.len_utf8(),
+        })
+    }
+
+    /// Returns the span of the count along with the count.
+    fn count(&mut self) -> Result<(Range<usize>, usize), ParseError> {
+        let start = self.position();
+        while self
+            .chars
+            .next_if(|(_, character)| character.is_ascii_digit())
+            .is_some()
+        {}
+
+        let span = start..self.position();
+        match self.input[span.clone()].parse() {
+            Ok(count) => Ok((span, count)),
+            Err(_) => Err(ParseError::BadCount { span }),
+        }
+    }
+
+    /// The byte index of the next character.
+    fn position(&mut self) -> usize {
+        self.chars
+            .peek()
+            .map_or(self.input.len(), |&(index, _)| index)
+    }
+}
@@ -1,31 +1,78 @@
+use std::{fmt, str::FromStr};
+
 use rand::Rng;
 use strum::{EnumCount, IntoEnumIterator};
 use tetrominos::Tetromino;
 
+use crate::{parsing::Parser, ParseError};
+
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct Pattern {
     pub tetromino_set: [usize; Tetromino::COUNT],
     pub draw_count: usize,
+    /// Whether a drawn tetromino stays in the set, so that it can be drawn again.
+    pub replacement: bool,
 }
 
 impl Pattern {
+    /// A pattern drawing exactly `variant`.
+    pub fn single(variant: Tetromino) -> Self {
+        let mut tetromino_set = [0; Tetromino::COUNT];
+        tetromino_set[variant as usize] = 1;
+        Self {
+            tetromino_set,
+            draw_count: 1,
+            replacement: false,
+        }
+    }
+
     pub fn size(&self) -> usize {
         self.tetromino_set.iter().sum()
     }
 
-    pub fn draw(&mut self) -> Option<Tetromino> {
-        let total_weight = self.size();
-        if total_weight == 0 {
+    pub fn is_empty(&self) -> bool {
+        self.draw_count == 0 || self.size() == 0
+    }
+
+    /// Every tetromino the next draw could yield.
+    pub fn candidates(&self) -> Vec<Tetromino> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+
+        Tetromino::iter()
+            .filter(|&variant| self.tetromino_set[variant as usize] > 0)
+            .collect()
+    }
+
+    /// Draws `variant` instead of a random tetromino, if the pattern can yield it.
+    pub fn take(&mut self, variant: Tetromino) -> bool {
+        if self.is_empty() || self.tetromino_set[variant as usize] == 0 {
+            return false;
+        }
+
+        if !self.replacement {
+            self.tetromino_set[variant as usize] -= 1;
+        }
+        self.draw_count -= 1;
+        true
+    }
+
+    pub fn draw(&mut self, rng: &mut impl Rng) -> Option<Tetromino> {
+        if self.is_empty() {
             return None;
         }
+        let total_weight = self.size();
 
-        let bar = rand::thread_rng().gen_range(0..total_weight);
+        let bar = rng.gen_range(0..total_weight);
 
         let mut cumulative_weight = 0;
         for (i, weight) in self.tetromino_set.iter_mut().enumerate() {
             cumulative_weight += *weight;
             if bar < cumulative_weight {
-                *weight -= 1;
+                if !self.replacement {
+                    *weight -= 1;
+                }
                 self.draw_count -= 1;
                 return Some(Tetromino::from(i));
             }
@@ -40,50 +87,52 @@ impl Default for Pattern {
         Self {
             tetromino_set: [1; Tetromino::COUNT],
             draw_count: Tetromino::COUNT,
+            replacement: false,
         }
     }
 }
 
-impl super::Parsing for Pattern {
-    fn parse<T: Into<String>>(input: T) -> Result<Self, String> {
-        let input = input.into();
-
-        let validation = regex::Regex::new(r"^((?<pattern>\[.+?\])|(?<all>\*))(p(?<amount>\d+))?$")
-            .map_err(|_| "Invalid regex pattern!")?;
-
-        let captures = validation
-            .captures(input.as_str())
-            .ok_or("Invalid pattern format!")?;
-
-        let collection: Vec<_> = if captures.name("all").is_some() {
-            Tetromino::iter().collect()
-        } else if let Some(capture) = captures.name("pattern") {
-            capture.as_str()[1..capture.len() - 1]
-                .chars()
-                .map(super::Tetromino::parse)
-                .collect::<Result<Vec<_>, _>>()?
-        } else {
-            return Err(String::from("Invalid tetromino set!"));
-        };
-
-        let draw_count = captures
-            .name("amount")
-            .map_or(1, |r#match| r#match.as_str().parse::<usize>().unwrap_or(1));
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let size = self.size();
+        if size == 1 && self.draw_count == 1 && !self.replacement {
+            let variant = Tetromino::iter()
+                .find(|&variant| self.tetromino_set[variant as usize] == 1)
+                .expect("the set holds one tetromino");
+            return write!(f, "{variant:?}");
+        }
 
-        if draw_count > collection.len() {
-            return Err(String::from(
-                "Amount may not be greater than the number of tetrominos in the tetromino set!",
-            ));
+        if self.tetromino_set == [1; Tetromino::COUNT] {
+            write!(f, "*")?;
+        } else {
+            write!(f, "[")?;
+            for variant in Tetromino::iter() {
+                for _ in 0..self.tetromino_set[variant as usize] {
+                    write!(f, "{variant:?}")?;
+                }
+            }
+            write!(f, "]")?;
         }
 
-        let mut tetromino_set = [0; Tetromino::COUNT];
-        for variant in collection {
-            tetromino_set[variant as usize] += 1;
+        match self.draw_count {
+            count if self.replacement => write!(f, "r{count}"),
+            1 => Ok(()),
+            count if count == size => write!(f, "!"),
+            count => write!(f, "p{count}"),
         }
+    }
+}
 
-        Ok(Self {
-            tetromino_set,
-            draw_count,
-        })
+impl FromStr for Pattern {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Parser::new(s).single_pattern()
+    }
+}
+
+impl super::Parsing for Pattern {
+    fn parse<T: Into<String>>(input: T) -> Result<Self, ParseError> {
+        input.into().parse()
     }
 }
@@ -0,0 +1,398 @@
+use std::{collections::VecDeque, fmt};
+
+use rand::{Rng, RngCore};
+use strum::{EnumCount, IntoEnumIterator};
+use tetrominos::Tetromino;
+
+use crate::Pattern;
+
+/// Decides which tetrominos a [`Queue`](crate::Queue) yields.
+pub trait Randomizer {
+    /// Draws the next tetromino, or `None` once the randomizer has run out.
+    fn draw(&mut self, rng: &mut dyn RngCore) -> Option<Tetromino>;
+
+    /// Every tetromino the next draw could yield.
+    fn candidates(&self) -> Vec<Tetromino>;
+
+    /// Draws `variant` instead of a random tetromino, if it can be drawn next.
+    fn take(&mut self, variant: Tetromino) -> bool;
+
+    /// Number of tetrominos left to draw, or `None` if the randomizer never runs out.
+    fn remaining(&self) -> Option<usize>;
+
+    /// The part of the state that a draw or a take can change.
+    type Saved: fmt::Debug + Clone;
+
+    /// Saves what the next draw or take changes, which is cheap however much is left to draw.
+    fn save(&self) -> Self::Saved;
+
+    /// Puts back the state that [`Randomizer::save`] saved, provided that at most one draw or
+    /// take has happened since.
+    fn restore(&mut self, saved: Self::Saved);
+}
+
+/// Draws from a sequence of patterns, one after the other.
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq)]
+pub struct Patterns {
+    pub sequence: VecDeque<Pattern>,
+    /// Whether full bags are drawn once the sequence runs out.
+    pub endless: bool,
+}
+
+impl Patterns {
+    /// Index of the pattern the next tetromino is drawn from, skipping the empty ones.
+    pub fn current(&self) -> Option<usize> {
+        self.sequence.iter().position(|pattern| !pattern.is_empty())
+    }
+
+    /// Like [`Patterns::current`], but starts a new full bag once an endless sequence runs out.
+    fn current_or_refill(&mut self) -> Option<usize> {
+        match self.current() {
+            Some(index) => Some(index),
+            None if self.endless => {
+                self.sequence.push_back(Pattern::default());
+                Some(self.sequence.len() - 1)
+            }
+            None => None,
+        }
+    }
+
+    /// Drops the pattern at `index` once it has yielded everything.
+    fn consume(&mut self, index: usize) {
+        if self.sequence[index].is_empty() {
+            self.sequence.remove(index);
+        }
+    }
+}
+
+impl Randomizer for Patterns {
+    fn draw(&mut self, mut rng: &mut dyn RngCore) -> Option<Tetromino> {
+        let index = self.current_or_refill()?;
+        let variant = self.sequence[index].draw(&mut rng);
+        self.consume(index);
+        variant
+    }
+
+    fn candidates(&self) -> Vec<Tetromino> {
+        match self.current() {
+            Some(index) => self.sequence[index].candidates(),
+            None if self.endless => Pattern::default().candidates(),
+            None => Vec::new(),
+        }
+    }
+
+    fn take(&mut self, variant: Tetromino) -> bool {
+        let Some(index) = self.current_or_refill() else {
+            return false;
+        };
+        let taken = self.sequence[index].take(variant);
+        self.consume(index);
+        taken
+    }
+
+    fn remaining(&self) -> Option<usize> {
+        if self.endless {
+            return None;
+        }
+        Some(self.sequence.iter().map(|pattern| pattern.draw_count).sum())
+    }
+
+    /// The length of the sequence along with the current pattern, as the patterns behind it are
+    /// left untouched.
+    type Saved = (usize, usize, Option<Pattern>);
+
+    fn save(&self) -> Self::Saved {
+        let index = self.current().unwrap_or(self.sequence.len());
+        (
+            self.sequence.len(),
+            index,
+            self.sequence.get(index).cloned(),
+        )
+    }
+
+    fn restore(&mut self, (length, index, pattern): Self::Saved) {
+        self.sequence.truncate(length);
+        if let Some(pattern) = pattern {
+            if self.sequence.len() < length {
+                self.sequence.insert(index, pattern);
+            } else {
+                self.sequence[index] = pattern;
+            }
+        }
+    }
+}
+
+/// Draws every variant once in a random order, then starts over with a new bag.
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq)]
+pub struct Bag {
+    pub bag: Pattern,
+}
+
+impl Randomizer for Bag {
+    fn draw(&mut self, mut rng: &mut dyn RngCore) -> Option<Tetromino> {
+        if self.bag.is_empty() {
+            self.bag = Pattern::default();
+        }
+        self.bag.draw(&mut rng)
+    }
+
+    fn candidates(&self) -> Vec<Tetromino> {
+        if self.bag.is_empty() {
+            return Pattern::default().candidates();
+        }
+        self.bag.candidates()
+    }
+
+    fn take(&mut self, variant: Tetromino) -> bool {
+        if self.bag.is_empty() {
+            self.bag = Pattern::default();
+        }
+        self.bag.take(variant)
+    }
+
+    fn remaining(&self) -> Option<usize> {
+        None
+    }
+
+    type Saved = Pattern;
+
+    fn save(&self) -> Self::Saved {
+        self.bag.clone()
+    }
+
+    fn restore(&mut self, saved: Self::Saved) {
+        self.bag = saved;
+    }
+}
+
+/// Draws every variant with the same chance, independently of the previous draws.
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq)]
+pub struct Uniform;
+
+impl Randomizer for Uniform {
+    fn draw(&mut self, rng: &mut dyn RngCore) -> Option<Tetromino> {
+        Some(Tetromino::from(rng.gen_range(0..Tetromino::COUNT)))
+    }
+
+    fn candidates(&self) -> Vec<Tetromino> {
+        Tetromino::iter().collect()
+    }
+
+    fn take(&mut self, _: Tetromino) -> bool {
+        true
+    }
+
+    fn remaining(&self) -> Option<usize> {
+        None
+    }
+
+    type Saved = ();
+
+    fn save(&self) -> Self::Saved {}
+
+    fn restore(&mut self, _: Self::Saved) {}
+}
+
+/// Draws uniformly among the variants that are not among the last `length` draws.
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq)]
+pub struct History {
+    /// Capped below the number of variants, so that there is always one left to draw.
+    pub length: usize,
+    pub history: VecDeque<Tetromino>,
+}
+
+impl History {
+    pub fn new(length: usize) -> Self {
+        Self {
+            length: length.min(Tetromino::COUNT - 1),
+            history: VecDeque::new(),
+        }
+    }
+
+    fn record(&mut self, variant: Tetromino) {
+        self.history.push_back(variant);
+        while self.history.len() > self.length {
+            self.history.pop_front();
+        }
+    }
+}
+
+impl Randomizer for History {
+    fn draw(&mut self, rng: &mut dyn RngCore) -> Option<Tetromino> {
+        let candidates = self.candidates();
+        let variant = candidates[rng.gen_range(0..candidates.len())];
+        self.record(variant);
+        Some(variant)
+    }
+
+    fn candidates(&self) -> Vec<Tetromino> {
+        Tetromino::iter()
+            .filter(|variant| !self.history.contains(variant))
+            .collect()
+    }
+
+    fn take(&mut self, variant: Tetromino) -> bool {
+        if self.history.contains(&variant) {
+            return false;
+        }
+        self.record(variant);
+        true
+    }
+
+    fn remaining(&self) -> Option<usize> {
+        None
+    }
+
+    /// The history, which is never longer than the number of variants.
+    type Saved = [Option<Tetromino>; Tetromino::COUNT];
+
+    fn save(&self) -> Self::Saved {
+        let mut saved = [None; Tetromino::COUNT];
+        for (slot, &variant) in saved.iter_mut().zip(self.history.iter()) {
+            *slot = Some(variant);
+        }
+        saved
+    }
+
+    fn restore(&mut self, saved: Self::Saved) {
+        self.history.clear();
+        self.history.extend(saved.into_iter().flatten());
+    }
+}
+
+/// Cycles through a fixed list of tetrominos forever.
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq)]
+pub struct Cyclic {
+    pub cycle: Vec<Tetromino>,
+    /// Index of the next tetromino in the cycle.
+    pub position: usize,
+}
+
+impl Cyclic {
+    pub fn new(cycle: Vec<Tetromino>) -> Self {
+        Self { cycle, position: 0 }
+    }
+}
+
+impl Randomizer for Cyclic {
+    fn draw(&mut self, _: &mut dyn RngCore) -> Option<Tetromino> {
+        let variant = *self.cycle.get(self.position)?;
+        self.position = (self.position + 1) % self.cycle.len();
+        Some(variant)
+    }
+
+    fn candidates(&self) -> Vec<Tetromino> {
+        self.cycle.get(self.position).copied().into_iter().collect()
+    }
+
+    fn take(&mut self, variant: Tetromino) -> bool {
+        if self.cycle.get(self.position) != Some(&variant) {
+            return false;
+        }
+        self.position = (self.position + 1) % self.cycle.len();
+        true
+    }
+
+    fn remaining(&self) -> Option<usize> {
+        self.cycle.is_empty().then_some(0)
+    }
+
+    type Saved = usize;
+
+    fn save(&self) -> Self::Saved {
+        self.position
+    }
+
+    fn restore(&mut self, saved: Self::Saved) {
+        self.position = saved;
+    }
+}
+
+/// One of the randomizers, selected per queue.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum RandomizerMode {
+    Patterns(Patterns),
+    Bag(Bag),
+    Uniform(Uniform),
+    History(History),
+    Cyclic(Cyclic),
+}
+
+/// Forwards `$call` to whichever randomizer `$mode` selects, bound to `$randomizer`.
+macro_rules! forward {
+    ($mode:expr, $randomizer:ident => $call:expr) => {
+        match $mode {
+            RandomizerMode::Patterns($randomizer) => $call,
+            RandomizerMode::Bag($randomizer) => $call,
+            RandomizerMode::Uniform($randomizer) => $call,
+            RandomizerMode::History($randomizer) => $call,
+            RandomizerMode::Cyclic($randomizer) => $call,
+        }
+    };
+}
+
+impl Randomizer for RandomizerMode {
+    fn draw(&mut self, rng: &mut dyn RngCore) -> Option<Tetromino> {
+        forward!(self, randomizer => randomizer.draw(rng))
+    }
+
+    fn candidates(&self) -> Vec<Tetromino> {
+        forward!(self, randomizer => randomizer.candidates())
+    }
+
+    fn take(&mut self, variant: Tetromino) -> bool {
+        forward!(self, randomizer => randomizer.take(variant))
+    }
+
+    fn remaining(&self) -> Option<usize> {
+        forward!(self, randomizer => randomizer.remaining())
+    }
+
+    type Saved = SavedMode;
+
+    fn save(&self) -> Self::Saved {
+        match self {
+            RandomizerMode::Patterns(randomizer) => SavedMode::Patterns(randomizer.save()),
+            RandomizerMode::Bag(randomizer) => SavedMode::Bag(randomizer.save()),
+            RandomizerMode::Uniform(_) => SavedMode::Uniform,
+            RandomizerMode::History(randomizer) => SavedMode::History(randomizer.save()),
+            RandomizerMode::Cyclic(randomizer) => SavedMode::Cyclic(randomizer.save()),
+        }
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `saved` was saved from another kind of randomizer.
+    fn restore(&mut self, saved: Self::Saved) {
+        match (self, saved) {
+            (RandomizerMode::Patterns(randomizer), SavedMode::Patterns(saved)) => {
+                randomizer.restore(saved)
+            }
+            (RandomizerMode::Bag(randomizer), SavedMode::Bag(saved)) => randomizer.restore(saved),
+            (RandomizerMode::Uniform(_), SavedMode::Uniform) => {}
+            (RandomizerMode::History(randomizer), SavedMode::History(saved)) => {
+                randomizer.restore(saved)
+            }
+            (RandomizerMode::Cyclic(randomizer), SavedMode::Cyclic(saved)) => {
+                randomizer.restore(saved)
+            }
+            _ => panic!("restored a randomizer from the state of another kind"),
+        }
+    }
+}
+
+/// What [`RandomizerMode::save`] saves, for whichever randomizer is selected.
+#[derive(Debug, Clone)]
+pub enum SavedMode {
+    Patterns(<Patterns as Randomizer>::Saved),
+    Bag(<Bag as Randomizer>::Saved),
+    Uniform,
+    History(<History as Randomizer>::Saved),
+    Cyclic(<Cyclic as Randomizer>::Saved),
+}
+
+impl Default for RandomizerMode {
+    fn default() -> Self {
+        RandomizerMode::Bag(Bag::default())
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::VecDeque;
+
+use strum::IntoEnumIterator;
+use tetrominos::Tetromino;
+
+use crate::{Pattern, Patterns, Queue, RandomizerMode};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sequence {
+    pub tetrominos: Vec<Tetromino>,
+    /// Number of ways to draw the tetrominos when identical ones in a set are told apart.
+    pub count: usize,
+    /// The chance of the queue yielding exactly these tetrominos.
+    pub probability: f64,
+}
+
+impl Queue {
+    /// Every distinct order of tetrominos the queue can yield, sorted by the order of the
+    /// variants. Only the preview and the sequence of patterns are enumerated, which leaves out
+    /// what an endless sequence or another randomizer draws.
+    pub fn sequences(&self) -> Vec<Sequence> {
+        let mut sequences = Vec::new();
+        let mut drawn: Vec<_> = self.preview.iter().copied().collect();
+        let patterns: Vec<_> = self
+            .patterns()
+            .iter()
+            .flat_map(|patterns| patterns.sequence.iter().cloned())
+            .collect();
+        Self::enumerate(&patterns, &mut drawn, 1, &mut sequences);
+
+        let total: usize = sequences.iter().map(|sequence| sequence.count).sum();
+        for sequence in sequences.iter_mut() {
+            sequence.probability = sequence.count as f64 / total as f64;
+        }
+        sequences
+    }
+
+    fn enumerate(
+        patterns: &[Pattern],
+        drawn: &mut Vec<Tetromino>,
+        count: usize,
+        sequences: &mut Vec<Sequence>,
+    ) {
+        let Some((pattern, rest)) = patterns.split_first() else {
+            sequences.push(Sequence {
+                tetrominos: drawn.clone(),
+                count,
+                probability: 0.0,
+            });
+            return;
+        };
+        if pattern.is_empty() {
+            return Self::enumerate(rest, drawn, count, sequences);
+        }
+
+        for variant in Tetromino::iter() {
+            let weight = pattern.tetromino_set[variant as usize];
+            if weight == 0 {
+                continue;
+            }
+
+            let mut remaining = Vec::with_capacity(patterns.len());
+            let mut pattern = pattern.clone();
+            pattern.take(variant);
+            remaining.push(pattern);
+            remaining.extend_from_slice(rest);
+
+            drawn.push(variant);
+            Self::enumerate(&remaining, drawn, count * weight, sequences);
+            drawn.pop();
+        }
+    }
+
+    /// A finite queue that yields exactly `tetrominos`, in order.
+    pub fn fixed(tetrominos: Vec<Tetromino>) -> Self {
+        let sequence: VecDeque<_> = tetrominos.into_iter().map(Pattern::single).collect();
+
+        Self {
+            randomizer: RandomizerMode::Patterns(Patterns {
+                sequence,
+                endless: false,
+            }),
+            ..Default::default()
+        }
+    }
+}
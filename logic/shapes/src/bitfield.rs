@@ -1,3 +1,5 @@
+use std::fmt;
+
 use colored::Colorize;
 use orientation::{Axis, Direction};
 use strum::EnumCount;
@@ -22,6 +24,7 @@ impl Bitfield {
         (self.value >> Self::index(x, y, z)) & 1 == 1
     }
 
+    #[allow(clippy::unusual_byte_groupings)]
     pub const fn canonical(variant: Tetromino) -> Self {
         // for consistency in the canonical representation (pos y):
         // the projecting part of the shape always tries to be as far away from the origin
@@ -68,20 +71,36 @@ impl Bitfield {
         Self { value }
     }
 
+    /// The same cells, translated so that the bounding box starts at the origin.
+    pub fn normalized(&self) -> Self {
+        let [start_x, start_y, start_z] = self.compute_bounding_box().start;
+        let mut value = 0;
+
+        for y in start_y..FIELD_SIZE {
+            for z in start_z..FIELD_SIZE {
+                for x in start_x..FIELD_SIZE {
+                    if self.is_filled(x, y, z) {
+                        value |= 1 << Self::index(x - start_x, y - start_y, z - start_z);
+                    }
+                }
+            }
+        }
+
+        Self { value }
+    }
+
     pub fn compute_bitboards(&self) -> [u64; FIELD_SIZE] {
         let mut layers = [0; FIELD_SIZE];
 
-        for y in 0..FIELD_SIZE {
-            let mut layer = 0;
+        for (y, layer) in layers.iter_mut().enumerate() {
             for z in 0..FIELD_SIZE {
                 for x in 0..FIELD_SIZE {
                     if self.is_filled(x, y, z) {
                         let index = x + z * layer::MAX_SIZE;
-                        layer |= 1 << index;
+                        *layer |= 1 << index;
                     }
                 }
             }
-            layers[y] = layer;
         }
 
         layers
@@ -114,8 +133,8 @@ impl Bitfield {
     }
 }
 
-impl ToString for Bitfield {
-    fn to_string(&self) -> String {
+impl fmt::Display for Bitfield {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut result = String::new();
         for y in (0..FIELD_SIZE).rev() {
             result.push('\n');
@@ -132,7 +151,7 @@ impl ToString for Bitfield {
             }
         }
 
-        result
+        write!(f, "{result}")
     }
 }
 
@@ -164,12 +183,12 @@ pub mod tests {
         let variant = Tetromino::I;
         let bitfield = Bitfield::canonical(variant);
 
-        println!("{}", bitfield.to_string());
+        println!("{bitfield}");
 
         let bitboards = bitfield.compute_bitboards();
 
-        for i in 0..bitboards.len() {
-            println!("{}", bitboard_to_string(bitboards[i], variant));
+        for bitboard in bitboards {
+            println!("{}", bitboard_to_string(bitboard, variant));
         }
     }
 
@@ -177,7 +196,7 @@ pub mod tests {
     fn rotation() {
         let old_bitfield = Bitfield::canonical(Tetromino::L);
 
-        println!("Old bitfield: {}", old_bitfield.to_string());
+        println!("Old bitfield: {old_bitfield}");
 
         let new_bitfield = old_bitfield.rotate(
             Direction {
@@ -187,6 +206,6 @@ pub mod tests {
             3,
         );
 
-        println!("New bitfield: {}", new_bitfield.to_string());
+        println!("New bitfield: {new_bitfield}");
     }
 }
@@ -1,3 +1,5 @@
+use std::fmt;
+
 use colored::Colorize;
 use orientation::Axis;
 use strum::EnumCount;
@@ -21,15 +23,15 @@ impl BoundingBox {
     }
 }
 
-impl ToString for BoundingBox {
-    fn to_string(&self) -> String {
+impl fmt::Display for BoundingBox {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut result = String::new();
 
         for y in 0..super::MAX_SIZE {
-            result.push_str("\n");
+            result.push('\n');
             result.push_str(&format!("Bounding box layer: {}", y));
             for z in (0..super::MAX_SIZE).rev() {
-                result.push_str("\n");
+                result.push('\n');
                 for x in 0..super::MAX_SIZE {
                     if self.start == [x, y, z] {
                         result.push_str(&"  ".on_blue().to_string());
@@ -42,6 +44,6 @@ impl ToString for BoundingBox {
             }
         }
 
-        result
+        write!(f, "{result}")
     }
 }
@@ -0,0 +1,73 @@
+use orientation::Axis;
+use strum::EnumCount;
+
+use crate::ShapeId;
+
+/// The orientation a shape shares its cells with, and the translation that aligns the two.
+///
+/// A piece with the original shape at `position` covers the same cells
+/// as a piece with the canonical shape at `position + offset`.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct Canonical {
+    pub shape_id: ShapeId,
+    pub offset: [isize; Axis::COUNT],
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use orientation::{Axis, Direction, Orientation, Polarity, Rotation};
+    use strum::{EnumCount, IntoEnumIterator};
+    use tetrominos::Tetromino;
+
+    use crate::{Shape, ShapeId};
+
+    fn shape_ids(variant: Tetromino) -> Vec<ShapeId> {
+        let mut shape_ids = Vec::new();
+        for polarity in Polarity::iter() {
+            for axis in Axis::iter() {
+                for rotation in Rotation::iter() {
+                    let direction = Direction::from((polarity, axis));
+                    let orientation = Orientation::from((direction, rotation));
+                    shape_ids.push(ShapeId::from((variant, orientation)));
+                }
+            }
+        }
+        shape_ids
+    }
+
+    #[test]
+    fn symmetric_pieces() {
+        for (variant, expected) in [(Tetromino::I, 3), (Tetromino::O, 3), (Tetromino::T, 12)] {
+            let canonicals: HashSet<_> = shape_ids(variant)
+                .iter()
+                .map(|shape_id| shape_id.canonical().shape_id)
+                .collect();
+
+            assert_eq!(canonicals.len(), expected, "{variant:?}");
+        }
+    }
+
+    #[test]
+    fn same_cells() {
+        for variant in Tetromino::iter() {
+            for shape_id in shape_ids(variant) {
+                let canonical = shape_id.canonical();
+                let shape: &Shape = (&shape_id).into();
+                let canonical_shape: &Shape = (&canonical.shape_id).into();
+
+                assert_eq!(
+                    shape.bitfield.normalized().value,
+                    canonical_shape.bitfield.normalized().value
+                );
+                for axis in 0..Axis::COUNT {
+                    assert_eq!(
+                        shape.bounding_box.start[axis] as isize,
+                        canonical_shape.bounding_box.start[axis] as isize + canonical.offset[axis]
+                    );
+                }
+            }
+        }
+    }
+}
@@ -1,5 +1,3 @@
-use std::time::Instant;
-
 use lazy_static::lazy_static;
 use orientation::{Axis, Direction, Orientation, Polarity, Rotation};
 use strum::{EnumCount, IntoEnumIterator};
@@ -7,11 +5,13 @@ use tetrominos::Tetromino;
 
 mod bitfield;
 mod bounding_box;
+mod canonical;
 mod shape;
 mod shape_id;
 
 pub use bitfield::Bitfield;
 pub use bounding_box::BoundingBox;
+pub use canonical::Canonical;
 pub use shape::Shape;
 pub use shape_id::ShapeId;
 
@@ -21,11 +21,10 @@ lazy_static! {
     pub static ref SHAPES: [Shape; ShapeId::COUNT] = generate_shapes();
     pub static ref ROTATIONS: [Orientation; Orientation::COUNT * Direction::COUNT] =
         generate_rotations();
+    pub static ref CANONICALS: [Canonical; ShapeId::COUNT] = generate_canonicals();
 }
 
 fn generate_rotations() -> [Orientation; Orientation::COUNT * Direction::COUNT] {
-    let start = Instant::now();
-
     let mut rotations = [Orientation::default(); Orientation::COUNT * Direction::COUNT];
 
     for axis in Axis::iter() {
@@ -43,11 +42,6 @@ fn generate_rotations() -> [Orientation; Orientation::COUNT * Direction::COUNT]
         }
     }
 
-    println!(
-        "Finished computation of rotations in {:?}!",
-        start.elapsed()
-    );
-
     rotations
 }
 
@@ -499,8 +493,6 @@ fn rotate(orientation: Orientation, around: Direction) -> Orientation {
 }
 
 fn generate_shapes() -> [Shape; ShapeId::COUNT] {
-    let start = Instant::now();
-
     let mut shapes = [Shape {
         bitboards: [0; MAX_SIZE],
         bounding_box: BoundingBox {
@@ -624,7 +616,51 @@ fn generate_shapes() -> [Shape; ShapeId::COUNT] {
         }
     }
 
-    println!("Finished computation of shapes in {:?}", start.elapsed());
-
     shapes
 }
+
+fn generate_canonicals() -> [Canonical; ShapeId::COUNT] {
+    let mut shape_ids = [ShapeId::from(Tetromino::I); ShapeId::COUNT];
+    for variant in Tetromino::iter() {
+        for polarity in Polarity::iter() {
+            for axis in Axis::iter() {
+                for rotation in Rotation::iter() {
+                    let shape_id = ShapeId::from((
+                        variant,
+                        Orientation::from((Direction::from((polarity, axis)), rotation)),
+                    ));
+                    shape_ids[shape_id.combination()] = shape_id;
+                }
+            }
+        }
+    }
+
+    let canonicals = shape_ids.map(|shape_id| {
+        let shape: &Shape = (&shape_id).into();
+        let cells = shape.bitfield.normalized();
+
+        // the first orientation of the same tetromino that covers the same cells
+        let representative = shape_ids
+            .iter()
+            .copied()
+            .find(|other| {
+                let other_shape: &Shape = other.into();
+                other.variant == shape_id.variant && other_shape.bitfield.normalized() == cells
+            })
+            .expect("every shape is equivalent to itself");
+        let representative_shape: &Shape = (&representative).into();
+
+        let mut offset = [0; Axis::COUNT];
+        for (axis, component) in offset.iter_mut().enumerate() {
+            *component = shape.bounding_box.start[axis] as isize
+                - representative_shape.bounding_box.start[axis] as isize;
+        }
+
+        Canonical {
+            shape_id: representative,
+            offset,
+        }
+    });
+
+    canonicals
+}
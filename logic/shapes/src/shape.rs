@@ -63,6 +63,6 @@ mod tests {
         let shape: &Shape = (&shape_id).into();
         let &Shape { bitfield, .. } = shape;
 
-        println!("{}", bitfield.to_string());
+        println!("{bitfield}");
     }
 }
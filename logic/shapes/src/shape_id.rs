@@ -2,7 +2,7 @@ use orientation::{Direction, Orientation};
 use strum::EnumCount;
 use tetrominos::Tetromino;
 
-use crate::ROTATIONS;
+use crate::{Canonical, CANONICALS, ROTATIONS};
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub struct ShapeId {
@@ -21,6 +21,10 @@ impl ShapeId {
     pub fn combination(&self) -> usize {
         self.orientation.combination() + self.variant as usize * Orientation::COUNT
     }
+
+    pub fn canonical(&self) -> Canonical {
+        CANONICALS[self.combination()]
+    }
 }
 
 impl From<Tetromino> for ShapeId {
@@ -0,0 +1,70 @@
+use std::{error::Error, fmt, ops::Range};
+
+/// An error in a tetromino, queue or pattern string, along with the byte span it was found at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// A character that is neither the letter of a tetromino nor part of the pattern syntax.
+    UnknownTetromino { character: char, span: Range<usize> },
+    /// A count that is missing or too large to be represented.
+    BadCount { span: Range<usize> },
+    /// A count of tetrominos to draw without replacement that exceeds the size of the set.
+    CountTooLarge {
+        count: usize,
+        size: usize,
+        span: Range<usize>,
+    },
+    /// A hold slot that is not a single tetromino at the start of the queue.
+    MalformedHold { span: Range<usize> },
+    /// A set that contains no tetrominos but has to yield some.
+    EmptySet { span: Range<usize> },
+    /// A `[` without its `]`.
+    UnclosedSet { span: Range<usize> },
+    /// The input ended where a pattern was expected.
+    MissingPattern { span: Range<usize> },
+    /// An input with nothing to parse.
+    EmptyInput { span: Range<usize> },
+    /// Input left over after a single pattern.
+    TrailingInput { span: Range<usize> },
+}
+
+impl ParseError {
+    pub fn span(&self) -> Range<usize> {
+        match self {
+            ParseError::UnknownTetromino { span, .. }
+            | ParseError::BadCount { span }
+            | ParseError::CountTooLarge { span, .. }
+            | ParseError::MalformedHold { span }
+            | ParseError::EmptySet { span }
+            | ParseError::UnclosedSet { span }
+            | ParseError::MissingPattern { span }
+            | ParseError::EmptyInput { span }
+            | ParseError::TrailingInput { span } => span.clone(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::UnknownTetromino { character, .. } => {
+                write!(f, "Unknown tetromino '{character}'")?
+            }
+            ParseError::BadCount { .. } => write!(f, "Invalid count")?,
+            ParseError::CountTooLarge { count, size, .. } => write!(
+                f,
+                "Cannot draw {count} tetrominos from a set of {size} without replacement"
+            )?,
+            ParseError::MalformedHold { .. } => write!(f, "Malformed hold")?,
+            ParseError::EmptySet { .. } => write!(f, "Empty tetromino set")?,
+            ParseError::UnclosedSet { .. } => write!(f, "Unclosed tetromino set")?,
+            ParseError::MissingPattern { .. } => write!(f, "Missing pattern")?,
+            ParseError::EmptyInput { .. } => write!(f, "Empty input")?,
+            ParseError::TrailingInput { .. } => write!(f, "Unexpected input after the pattern")?,
+        }
+
+        let span = self.span();
+        write!(f, " at bytes {}..{}", span.start, span.end)
+    }
+}
+
+impl Error for ParseError {}
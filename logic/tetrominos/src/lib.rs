@@ -1,7 +1,13 @@
+use std::str::FromStr;
+
 use colored::Color;
-use strum_macros::{EnumCount, EnumIter, EnumString};
+use strum_macros::{EnumCount, EnumIter};
+
+mod error;
 
-#[derive(Debug, EnumCount, EnumIter, EnumString, Clone, Copy, Hash, PartialEq, Eq)]
+pub use error::ParseError;
+
+#[derive(Debug, EnumCount, EnumIter, Clone, Copy, Hash, PartialEq, Eq)]
 #[repr(usize)]
 pub enum Tetromino {
     I,
@@ -72,3 +78,40 @@ impl From<usize> for Tetromino {
         }
     }
 }
+
+impl TryFrom<char> for Tetromino {
+    type Error = ParseError;
+
+    fn try_from(value: char) -> Result<Self, Self::Error> {
+        match value {
+            'I' => Ok(Tetromino::I),
+            'O' => Ok(Tetromino::O),
+            'T' => Ok(Tetromino::T),
+            'L' => Ok(Tetromino::L),
+            'S' => Ok(Tetromino::S),
+            'B' => Ok(Tetromino::B),
+            'D' => Ok(Tetromino::D),
+            'F' => Ok(Tetromino::F),
+            character => Err(ParseError::UnknownTetromino {
+                character,
+                span: 0..character.len_utf8(),
+            }),
+        }
+    }
+}
+
+/// Parses the letter of a tetromino, which has to make up the whole string.
+impl FromStr for Tetromino {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.char_indices();
+        match (chars.next(), chars.next()) {
+            (Some((_, character)), None) => Tetromino::try_from(character),
+            (Some(_), Some((index, _))) => Err(ParseError::TrailingInput {
+                span: index..s.len(),
+            }),
+            (None, _) => Err(ParseError::EmptyInput { span: 0..0 }),
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use orientation::{Axis, Direction, Polarity};
-use strum::IntoEnumIterator;
+use strum::{EnumCount, IntoEnumIterator};
 use strum_macros::EnumIter;
 
 #[derive(EnumIter, Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -10,6 +10,7 @@ pub enum Action {
     MoveRight,
     SoftDrop,
     HardDrop,
+    Hold,
     Rotate { direction: Direction },
 }
 
@@ -21,6 +22,18 @@ impl Action {
         )
     }
 
+    /// The translation applied to the piece by a movement or a soft drop.
+    pub fn offset(&self) -> Option<[isize; Axis::COUNT]> {
+        match self {
+            Self::MoveForward => Some([0, 0, 1]),
+            Self::MoveBackward => Some([0, 0, -1]),
+            Self::MoveLeft => Some([-1, 0, 0]),
+            Self::MoveRight => Some([1, 0, 0]),
+            Self::SoftDrop => Some([0, -1, 0]),
+            _ => None,
+        }
+    }
+
     pub fn get_all_actions() -> Vec<Self> {
         let mut actions = vec![
             Self::MoveForward,
@@ -29,6 +42,7 @@ impl Action {
             Self::MoveRight,
             Self::SoftDrop,
             Self::HardDrop,
+            Self::Hold,
         ];
 
         for axis in Axis::iter() {
@@ -1,45 +1,602 @@
-use std::{collections::HashSet, time::Duration};
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
 
-use crate::GameState;
+use crate::{
+    action::Action,
+    heuristic::{Evaluator, Weights},
+    transposition::{SharedTranspositionTable, TranspositionTable},
+    GameOutcome, GameState,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchConfig {
+    /// Number of pieces the search places at most.
+    pub max_depth: usize,
+    pub max_nodes: Option<usize>,
+    pub deadline: Option<Instant>,
+    /// Searches every depth up to `max_depth` in turn,
+    /// so that an interrupted search has still fully explored the shallower ones.
+    pub iterative_deepening: bool,
+    /// Number of entries of the transposition table, which bounds the memory of the search.
+    /// A parallel search has one for each child of the root it searches and one shared by all.
+    pub table_size: usize,
+}
+
+impl SearchConfig {
+    pub fn with_time_limit(mut self, limit: Duration) -> Self {
+        self.deadline = Some(Instant::now() + limit);
+        self
+    }
+
+    /// Whether a search that has explored `nodes` states has used up its node or time budget.
+    pub fn is_exhausted(&self, nodes: usize) -> bool {
+        self.max_nodes.is_some_and(|max_nodes| nodes >= max_nodes)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            max_depth: 8,
+            max_nodes: None,
+            deadline: None,
+            iterative_deepening: true,
+            table_size: 1 << 16,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult<const W: usize, const H: usize, const D: usize> {
+    pub nodes: usize,
+    pub best: Option<GameState<W, H, D>>,
+    pub best_score: f64,
+    /// The deepest depth up to which every state has been explored.
+    pub depth: usize,
+    /// Whether the search explored every state the game can reach, instead of running out of
+    /// nodes or time or stopping at `max_depth`.
+    pub complete: bool,
+}
+
+impl<const W: usize, const H: usize, const D: usize> SearchResult<W, H, D> {
+    fn new() -> Self {
+        Self {
+            nodes: 0,
+            best: None,
+            best_score: f64::NEG_INFINITY,
+            depth: 0,
+            complete: false,
+        }
+    }
+
+    /// Keeps the better of the two states. Ties are broken by the zobrist key,
+    /// so that the result does not depend on the order states are visited in.
+    fn consider(&mut self, game_state: &GameState<W, H, D>, score: f64) {
+        let is_better = match &self.best {
+            None => true,
+            Some(best) => {
+                score > self.best_score
+                    || (score == self.best_score && game_state.zobrist() < best.zobrist())
+            }
+        };
+
+        if is_better {
+            self.best_score = score;
+            self.best = Some(game_state.clone());
+        }
+    }
+}
 
 pub fn children<const W: usize, const H: usize, const D: usize>(
-    mut game_state: GameState<W, H, D>,
+    game_state: GameState<W, H, D>,
 ) -> Vec<GameState<W, H, D>> {
-    let mut children = Vec::new();
-    let actions = game_state.performable_actions();
+    scored_children(game_state, &Weights::default())
+        .into_iter()
+        .map(|(_, child)| child)
+        .collect()
+}
+
+/// The children that did not top out, along with their score, worst first.
+pub(crate) fn scored_children<
+    E: Evaluator<W, H, D> + ?Sized,
+    const W: usize,
+    const H: usize,
+    const D: usize,
+>(
+    mut game_state: GameState<W, H, D>,
+    evaluator: &E,
+) -> Vec<(f64, GameState<W, H, D>)> {
+    scored_moves(&mut game_state, evaluator)
+        .into_iter()
+        .map(|(score, actions)| {
+            let mut child = game_state.clone();
+            child.make(&actions);
+            (score, child)
+        })
+        .collect()
+}
 
-    for action in actions {
-        let mut child = game_state.clone();
-        child.perform(action, true);
-        if child.heuristic() > 0.0 {
-            children.push(child);
+/// The moves that do not top out, along with the score of the state they lead to, worst first.
+/// Every move is made and unmade on `game_state` to score it, instead of cloning it, which
+/// leaves it as it was, down to what it can redo.
+pub fn scored_moves<
+    E: Evaluator<W, H, D> + ?Sized,
+    const W: usize,
+    const H: usize,
+    const D: usize,
+>(
+    game_state: &mut GameState<W, H, D>,
+    evaluator: &E,
+) -> Vec<(f64, Vec<Action>)> {
+    let mut scored = Vec::new();
+    game_state.keeping_redo(|game_state| {
+        for actions in game_state.moves() {
+            game_state.make(&actions);
+            let topped_out = game_state.outcome() == GameOutcome::ToppedOut;
+            let score = evaluator.evaluate(game_state);
+            game_state.unmake(&actions);
+
+            if !topped_out {
+                scored.push((score, actions));
+            }
         }
+    });
+
+    scored.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+    scored
+}
+
+impl<const W: usize, const H: usize, const D: usize> GameState<W, H, D> {
+    /// The actions placing the current piece at each of its placements, and those placing
+    /// the piece that holding it brings out, which start with the hold.
+    pub fn moves(&mut self) -> Vec<Vec<Action>> {
+        let mut moves: Vec<_> = self
+            .placements()
+            .into_iter()
+            .map(|placement| placement.actions)
+            .collect();
+
+        self.keeping_redo(|game_state| {
+            if game_state.unlimited(|game_state| game_state.perform(Action::Hold, true)) {
+                moves.extend(game_state.placements().into_iter().map(|placement| {
+                    let mut actions = vec![Action::Hold];
+                    actions.extend(placement.actions);
+                    actions
+                }));
+                game_state.take_back();
+            }
+        });
+
+        moves
+    }
+
+    /// Performs a move, which [`GameState::unmake`] takes back whatever the undo limit.
+    /// Like performing an action, it discards what could have been redone.
+    pub fn make(&mut self, actions: &[Action]) {
+        self.unlimited(|game_state| {
+            for &action in actions {
+                game_state.perform(action, true);
+            }
+        });
     }
 
-    children.sort_by(|a, b| a.heuristic().total_cmp(&b.heuristic()));
-    children
+    /// Takes back a move made by [`GameState::make`], without offering it to redo.
+    pub fn unmake(&mut self, actions: &[Action]) {
+        for _ in actions {
+            self.take_back();
+        }
+    }
 }
 
 pub fn algorithm<const W: usize, const H: usize, const D: usize>(
     game_state: GameState<W, H, D>,
+    config: &SearchConfig,
+) -> SearchResult<W, H, D> {
+    algorithm_with(game_state, config, &Weights::default())
+}
+
+/// Like [`algorithm`], but scores the states with `evaluator` instead of the default weights.
+pub fn algorithm_with<
+    E: Evaluator<W, H, D> + ?Sized,
+    const W: usize,
+    const H: usize,
+    const D: usize,
+>(
+    mut game_state: GameState<W, H, D>,
+    config: &SearchConfig,
+    evaluator: &E,
+) -> SearchResult<W, H, D> {
+    let mut search = Search {
+        config,
+        evaluator,
+        memoization: TranspositionTable::new(config.table_size),
+        spent: None,
+        result: SearchResult::new(),
+        interrupted: false,
+        cut_off: false,
+    };
+
+    let first = if config.iterative_deepening {
+        0
+    } else {
+        config.max_depth
+    };
+    for depth in first..=config.max_depth {
+        search.memoization.clear();
+        search.cut_off = false;
+        search.visit(&mut game_state, depth);
+
+        if search.interrupted {
+            break;
+        }
+        search.result.depth = depth;
+
+        // nothing was left unexplored, so going deeper finds nothing new
+        if !search.cut_off {
+            break;
+        }
+    }
+
+    search.result.complete = !search.interrupted && !search.cut_off;
+    search.result
+}
+
+/// Searches the children of the root on `threads` worker threads. Before searching, the workers
+/// fill a shared transposition table that claims each state one move below the children for the
+/// first child leading to it, and only that child searches the state. Deeper down, each child is
+/// pruned with a table of its own, so what a child explores does not depend on how the workers
+/// are scheduled, and the results are merged in the order of the children. The result is the
+/// same for any number of threads, unless the search is interrupted.
+pub fn parallel_algorithm<const W: usize, const H: usize, const D: usize>(
+    game_state: GameState<W, H, D>,
+    config: &SearchConfig,
+    threads: usize,
+) -> SearchResult<W, H, D> {
+    parallel_algorithm_with(game_state, config, threads, &Weights::default())
+}
+
+/// Like [`parallel_algorithm`], but scores the states with `evaluator` instead of the default weights.
+pub fn parallel_algorithm_with<
+    E: Evaluator<W, H, D> + Sync + ?Sized,
+    const W: usize,
+    const H: usize,
+    const D: usize,
+>(
+    game_state: GameState<W, H, D>,
+    config: &SearchConfig,
+    threads: usize,
+    evaluator: &E,
+) -> SearchResult<W, H, D> {
+    let spent = AtomicUsize::new(0);
+    let roots = if game_state.outcome().is_running() {
+        scored_children(game_state.clone(), evaluator)
+    } else {
+        Vec::new()
+    };
+
+    // the states one move below the children are the same at every depth, so they are claimed once
+    let claims = SharedTranspositionTable::new(config.table_size);
+    if config.max_depth >= 2 {
+        in_parallel(&roots, threads, |index, (_, root)| {
+            let (Ok(owner), true) = (u16::try_from(index), root.outcome().is_running()) else {
+                return;
+            };
+            let mut root = root.clone();
+            for actions in root.moves() {
+                root.make(&actions);
+                claims.insert(root.zobrist(), owner);
+                root.unmake(&actions);
+            }
+        });
+    }
+
+    let mut result = SearchResult::new();
+    let mut interrupted = false;
+    let mut cut_off = false;
+
+    let first = if config.iterative_deepening {
+        0
+    } else {
+        config.max_depth
+    };
+    for depth in first..=config.max_depth {
+        if game_state.outcome() != GameOutcome::ToppedOut {
+            result.nodes += 1;
+            spent.fetch_add(1, Ordering::Relaxed);
+            result.consider(&game_state, evaluator.evaluate(&game_state));
+        }
+        if depth == 0 {
+            // the children are left to the next depth, if there are any
+            cut_off = game_state.outcome().is_running();
+            if !cut_off {
+                break;
+            }
+            continue;
+        }
+
+        let searched = in_parallel(&roots, threads, |index, (_, root)| {
+            let mut table = TranspositionTable::new(config.table_size);
+            let mut search = Search {
+                config,
+                evaluator,
+                memoization: Claimed {
+                    table: &mut table,
+                    claims: &claims,
+                    root: index,
+                    depth: depth - 1,
+                },
+                spent: Some(&spent),
+                result: SearchResult::new(),
+                interrupted: false,
+                cut_off: false,
+            };
+            search.visit(&mut root.clone(), depth - 1);
+            (search.result, search.interrupted, search.cut_off)
+        });
+
+        cut_off = false;
+        for (searched, searched_interrupted, searched_cut_off) in searched {
+            interrupted |= searched_interrupted;
+            cut_off |= searched_cut_off;
+            result.nodes += searched.nodes;
+            if let Some(best) = &searched.best {
+                result.consider(best, searched.best_score);
+            }
+        }
+
+        if interrupted {
+            break;
+        }
+        result.depth = depth;
+
+        if !cut_off {
+            break;
+        }
+    }
+
+    result.complete = !interrupted && !cut_off;
+    result
+}
+
+trait Memoization {
+    /// Whether `key` has already been explored with at least `depth` remaining,
+    /// recording it as explored otherwise.
+    fn is_explored(&mut self, key: u64, depth: usize) -> bool;
+}
+
+impl Memoization for TranspositionTable<usize> {
+    fn is_explored(&mut self, key: u64, depth: usize) -> bool {
+        if self.get(key).is_some_and(|explored| explored >= depth) {
+            return true;
+        }
+        self.insert(key, depth);
+        false
+    }
+}
+
+/// The memoization of the worker searching the child of the root at index `root`, which has
+/// `depth` left to search.
+///
+/// The states one move below the child are skipped if a child at a lower index claimed them,
+/// everything else is left to the worker's own table.
+struct Claimed<'a> {
+    table: &'a mut TranspositionTable<usize>,
+    claims: &'a SharedTranspositionTable,
+    root: usize,
     depth: usize,
-    memoization: &mut HashSet<GameState<W, H, D>>,
-    size: &mut usize,
-) {
-    if depth <= 0 || memoization.contains(&game_state) || game_state.heuristic() < 0.0 {
-        return;
+}
+
+impl Memoization for Claimed<'_> {
+    fn is_explored(&mut self, key: u64, depth: usize) -> bool {
+        if depth + 1 == self.depth
+            && self
+                .claims
+                .get(key)
+                .is_some_and(|owner| (owner as usize) < self.root)
+        {
+            return true;
+        }
+        self.table.is_explored(key, depth)
     }
+}
 
-    // if *size % 50 == 0 {
-    //     print!("\x1B[2J\x1B[1;1H");
-    //     println!("{}", game_state.to_string());
-    //     println!("Explored {size} states");
-    // }
+/// Calls `f` with each item and its index on `threads` worker threads,
+/// returning the results in the order of the items.
+fn in_parallel<T: Sync, R: Send>(
+    items: &[T],
+    threads: usize,
+    f: impl Fn(usize, &T) -> R + Sync,
+) -> Vec<R> {
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<_> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads.max(1))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut results = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(index) else {
+                            return results;
+                        };
+                        results.push((index, f(index, item)));
+                    }
+                })
+            })
+            .collect();
 
-    memoization.insert(game_state.clone());
-    *size += 1;
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("search workers do not panic"))
+            .collect()
+    });
+    results.sort_by_key(|&(index, _)| index);
+
+    results.into_iter().map(|(_, result)| result).collect()
+}
 
-    for child in children(game_state) {
-        algorithm(child, depth - 1, memoization, size);
+struct Search<'a, E: ?Sized, M, const W: usize, const H: usize, const D: usize> {
+    config: &'a SearchConfig,
+    evaluator: &'a E,
+    /// The deepest remaining depth each state has been explored with.
+    memoization: M,
+    /// Nodes explored by every search sharing the node budget.
+    spent: Option<&'a AtomicUsize>,
+    result: SearchResult<W, H, D>,
+    interrupted: bool,
+    cut_off: bool,
+}
+
+impl<
+        E: Evaluator<W, H, D> + ?Sized,
+        M: Memoization,
+        const W: usize,
+        const H: usize,
+        const D: usize,
+    > Search<'_, E, M, W, H, D>
+{
+    fn is_over_budget(&self) -> bool {
+        let spent = self
+            .spent
+            .map_or(self.result.nodes, |spent| spent.load(Ordering::Relaxed));
+
+        self.config.is_exhausted(spent)
+    }
+
+    /// Explores `game_state` up to `depth` more pieces deep, keeping the best state found.
+    fn visit(&mut self, game_state: &mut GameState<W, H, D>, depth: usize) {
+        if self.interrupted {
+            return;
+        }
+        if self.is_over_budget() {
+            self.interrupted = true;
+            return;
+        }
+
+        if game_state.outcome() == GameOutcome::ToppedOut
+            || self.memoization.is_explored(game_state.zobrist(), depth)
+        {
+            return;
+        }
+
+        self.result.nodes += 1;
+        if let Some(spent) = self.spent {
+            spent.fetch_add(1, Ordering::Relaxed);
+        }
+        self.result
+            .consider(game_state, self.evaluator.evaluate(game_state));
+
+        if !game_state.outcome().is_running() {
+            return;
+        }
+        if depth == 0 {
+            self.cut_off = true;
+            return;
+        }
+
+        for (_, actions) in scored_moves(game_state, self.evaluator) {
+            game_state.make(&actions);
+            self.visit(game_state, depth - 1);
+            game_state.unmake(&actions);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use queue::{Parsing, Queue};
+
+    use super::*;
+
+    #[test]
+    fn parallel_is_deterministic() {
+        let game_state = GameState::<4, 8, 4>::from(Queue::parse("[I]p1[O]p1[T]p1").unwrap());
+        let config = SearchConfig {
+            max_depth: 2,
+            ..Default::default()
+        };
+
+        let sequential = algorithm(game_state.clone(), &config);
+        let results =
+            [1, 2, 4].map(|threads| parallel_algorithm(game_state.clone(), &config, threads));
+
+        // the last piece is left unplaced, but every depth up to the maximum was searched
+        assert_eq!((results[0].depth, results[0].complete), (2, false));
+        assert_eq!(results[0].best_score, sequential.best_score);
+        for result in results.iter().skip(1) {
+            assert_eq!(result, &results[0]);
+            let (best, first) = (result.best.as_ref(), results[0].best.as_ref());
+            assert_eq!(best.unwrap().history, first.unwrap().history);
+        }
+    }
+
+    #[test]
+    fn parallel_shares_transpositions() {
+        let game_state = GameState::<4, 8, 4>::from(Queue::parse("[O]p1[O]p1[O]p1").unwrap());
+        let config = SearchConfig {
+            max_depth: 2,
+            iterative_deepening: false,
+            ..Default::default()
+        };
+
+        // placing two of the pieces either way round leads to the same state,
+        // which searching each child on its own would visit once for each
+        let roots = children(game_state.clone());
+        let separately = 1
+            + roots.len()
+            + roots
+                .into_iter()
+                .map(|root| children(root).len())
+                .sum::<usize>();
+
+        let results =
+            [1, 4].map(|threads| parallel_algorithm(game_state.clone(), &config, threads));
+        assert_eq!(results[0], results[1]);
+        assert!(results[0].nodes < separately / 2);
+    }
+
+    #[test]
+    fn make_unmake() {
+        let mut game_state = GameState::<4, 8, 4>::from(Queue::parse("TOI").unwrap());
+        let before = game_state.clone();
+
+        // scoring the moves leaves what the caller can redo alone
+        assert!(game_state.perform(Action::MoveRight, true));
+        game_state.undo();
+        let scored = scored_moves(&mut game_state, &Weights::default());
+        assert_eq!(game_state.redo(), Some(Action::MoveRight));
+        game_state.undo();
+
+        let children = scored_children(before.clone(), &Weights::default());
+        assert_eq!(scored.len(), children.len());
+        assert_eq!(game_state, before);
+        assert_eq!(game_state.history, before.history);
+
+        for ((score, actions), (child_score, child)) in scored.iter().zip(children.iter()) {
+            assert_eq!(score, child_score);
+            game_state.make(actions);
+            assert_eq!(&game_state, child);
+            game_state.unmake(actions);
+            assert_eq!(game_state, before);
+            assert_eq!(game_state.stats(), before.stats());
+            // unmaking leaves nothing behind to redo
+            assert_eq!(game_state.redo(), None);
+        }
+
+        // a state that keeps nothing to undo still takes back the moves it makes
+        let mut unrecorded = before.clone().with_undo_limit(0);
+        assert_eq!(unrecorded.moves(), game_state.moves());
+        assert_eq!(unrecorded, before);
+        for (_, actions) in scored.iter() {
+            unrecorded.make(actions);
+            unrecorded.unmake(actions);
+            assert_eq!(unrecorded, before);
+        }
+        assert!(unrecorded.undo.is_empty());
     }
 }
@@ -0,0 +1,326 @@
+use std::{collections::HashSet, str::FromStr};
+
+use layer::Layer;
+use queue::Queue;
+use tetrominos::Tetromino;
+
+use crate::{
+    brute_forcing::{scored_moves, SearchConfig},
+    pc_solver::{self, PcConfig},
+    GameState, Weights,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Goal {
+    /// Leave the playfield empty without building above `layers`.
+    PerfectClear {
+        layers: usize,
+    },
+    ClearLayers(usize),
+    /// Place that many pieces without topping out.
+    Survive(usize),
+}
+
+impl Goal {
+    fn is_met<const W: usize, const H: usize, const D: usize>(
+        &self,
+        game_state: &GameState<W, H, D>,
+    ) -> bool {
+        match *self {
+            Goal::PerfectClear { .. } => false,
+            Goal::ClearLayers(layers) => game_state.cleared_layers() >= layers,
+            Goal::Survive(pieces) => game_state.stats().pieces_placed >= pieces,
+        }
+    }
+}
+
+impl FromStr for Goal {
+    type Err = String;
+
+    /// Parses `pc`, `pc:<layers>`, `clear:<layers>` or `survive:<pieces>`.
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (name, amount) = match input.split_once(':') {
+            Some((name, amount)) => (
+                name,
+                Some(
+                    amount
+                        .parse::<usize>()
+                        .map_err(|_| format!("Invalid amount '{amount}'"))?,
+                ),
+            ),
+            None => (input, None),
+        };
+
+        match (name, amount) {
+            ("pc", layers) => Ok(Goal::PerfectClear {
+                layers: layers.unwrap_or(PcConfig::default().layers),
+            }),
+            ("clear", Some(layers)) => Ok(Goal::ClearLayers(layers)),
+            ("survive", Some(pieces)) => Ok(Goal::Survive(pieces)),
+            _ => Err(format!("Invalid goal '{input}'")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Coverage {
+    /// Number of distinct orders the queue can yield.
+    pub total: usize,
+    pub solved: usize,
+    /// The chance of drawing an order that achieves the goal, which unlike the share of solved
+    /// orders accounts for some orders being more likely than others.
+    pub probability: f64,
+    /// The orders that do not achieve the goal.
+    pub failures: Vec<Vec<Tetromino>>,
+    /// The orders the search ran out of its budget on before telling whether they achieve it,
+    /// which count as neither solved nor failed.
+    pub undecided: Vec<Vec<Tetromino>>,
+}
+
+impl Coverage {
+    /// The share of solved orders, in percent.
+    pub fn percentage(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        100.0 * self.solved as f64 / self.total as f64
+    }
+}
+
+/// Checks every order the queue can yield for whether the goal can be achieved from `playfield`.
+/// The piece held in the queue is kept for every order.
+///
+/// The search for each order places at most `config.max_depth` pieces and explores at most
+/// `config.max_nodes` states, and every search stops at `config.deadline`. Perfect clears are
+/// left to the solver, which keeps to the same budget but places as many pieces as their layers
+/// take instead of `config.max_depth`.
+pub fn coverage<const W: usize, const H: usize, const D: usize>(
+    playfield: &[Layer<W, D>; H],
+    queue: &Queue,
+    goal: Goal,
+    config: &SearchConfig,
+) -> Coverage {
+    let mut coverage = Coverage {
+        total: 0,
+        solved: 0,
+        probability: 0.0,
+        failures: Vec::new(),
+        undecided: Vec::new(),
+    };
+
+    for sequence in queue.sequences() {
+        let mut fixed = Queue::fixed(sequence.tetrominos.clone());
+        fixed.hold = queue.hold;
+        let game_state = GameState::<W, H, D>::from(fixed).with_playfield(*playfield);
+
+        coverage.total += 1;
+        match achieves(&game_state, goal, config) {
+            Some(true) => {
+                coverage.solved += 1;
+                coverage.probability += sequence.probability;
+            }
+            Some(false) => coverage.failures.push(sequence.tetrominos),
+            None => coverage.undecided.push(sequence.tetrominos),
+        }
+    }
+
+    coverage
+}
+
+/// Whether some sequence of placements achieves the goal from `game_state`, or `None` if the
+/// search ran out of the budget of `config` before it could tell.
+pub fn achieves<const W: usize, const H: usize, const D: usize>(
+    game_state: &GameState<W, H, D>,
+    goal: Goal,
+    config: &SearchConfig,
+) -> Option<bool> {
+    match goal {
+        Goal::PerfectClear { layers } => {
+            let pc_config = PcConfig {
+                layers,
+                ..Default::default()
+            };
+            pc_solver::solve_within(game_state, &pc_config, config)
+                .map(|solutions| !solutions.is_empty())
+        }
+        _ => {
+            let mut search = Search {
+                config,
+                goal,
+                nodes: 0,
+                dead_ends: HashSet::new(),
+            };
+            search.visit(&mut game_state.clone(), config.max_depth)
+        }
+    }
+}
+
+struct Search<'a, const W: usize, const H: usize, const D: usize> {
+    config: &'a SearchConfig,
+    goal: Goal,
+    nodes: usize,
+    /// States from which the goal cannot be achieved, however many pieces are placed.
+    dead_ends: HashSet<GameState<W, H, D>>,
+}
+
+impl<const W: usize, const H: usize, const D: usize> Search<'_, W, H, D> {
+    /// Whether the goal can be achieved from `game_state` within `depth` more pieces, or `None`
+    /// if the budget or the depth ran out first.
+    fn visit(&mut self, game_state: &mut GameState<W, H, D>, depth: usize) -> Option<bool> {
+        if self.goal.is_met(game_state) {
+            return Some(true);
+        }
+        if !game_state.outcome().is_running() || self.dead_ends.contains(game_state) {
+            return Some(false);
+        }
+        if depth == 0 || self.config.is_exhausted(self.nodes) {
+            return None;
+        }
+        self.nodes += 1;
+
+        // the moves come worst first, and the promising ones are the likelier to succeed
+        let mut decided = true;
+        for (_, actions) in scored_moves(game_state, &Weights::default())
+            .into_iter()
+            .rev()
+        {
+            game_state.make(&actions);
+            let achieved = self.visit(game_state, depth - 1);
+            game_state.unmake(&actions);
+            match achieved {
+                Some(true) => return Some(true),
+                Some(false) => {}
+                None => decided = false,
+            }
+        }
+
+        // a state is only known to be a dead end if nothing below it was cut short
+        if !decided {
+            return None;
+        }
+        self.dead_ends.insert(game_state.snapshot());
+        Some(false)
+    }
+}
+
+/// Parses a playfield from text, where every layer from the bottom upwards is given by `D` lines
+/// of `W` characters, the back one first, and layers are separated by empty lines.
+/// A tetromino letter marks a cell occupied by that variant and a `.` marks an empty one.
+pub fn parse_playfield<const W: usize, const H: usize, const D: usize>(
+    text: &str,
+) -> Result<[Layer<W, D>; H], String> {
+    let mut playfield = [Layer::default(); H];
+
+    let layers: Vec<Vec<&str>> = text
+        .trim()
+        .split("\n\n")
+        .filter(|layer| !layer.trim().is_empty())
+        .map(|layer| layer.lines().map(str::trim).collect())
+        .collect();
+    if layers.len() > H {
+        return Err(format!(
+            "Expected at most {H} layers, found {}",
+            layers.len()
+        ));
+    }
+
+    for (y, lines) in layers.iter().enumerate() {
+        if lines.len() != D {
+            return Err(format!(
+                "Expected {D} lines in layer {}, found {}",
+                y + 1,
+                lines.len()
+            ));
+        }
+
+        for (row, line) in lines.iter().enumerate() {
+            let z = D - 1 - row;
+            if line.chars().count() != W {
+                return Err(format!(
+                    "Expected {W} cells in line {} of layer {}",
+                    row + 1,
+                    y + 1
+                ));
+            }
+
+            for (x, cell) in line.chars().enumerate() {
+                if cell == '.' {
+                    continue;
+                }
+                let variant = Tetromino::try_from(cell).map_err(|error| error.to_string())?;
+                playfield[y].bitboards[variant as usize] |= 1 << (x + z * layer::MAX_SIZE);
+            }
+        }
+    }
+
+    Ok(playfield)
+}
+
+#[cfg(test)]
+mod tests {
+    use queue::Parsing;
+
+    use super::*;
+
+    #[test]
+    fn opener() {
+        let playfield = [Layer::default(); 8];
+        let pc = Goal::PerfectClear { layers: 1 };
+
+        // three pieces are too few to fill a layer
+        let config = SearchConfig::default();
+        let result =
+            coverage::<4, 8, 4>(&playfield, &Queue::parse("[OOOO]p3").unwrap(), pc, &config);
+        assert_eq!((result.solved, result.total), (0, 1));
+        assert_eq!(result.failures, [vec![Tetromino::O; 3]]);
+
+        let result =
+            coverage::<4, 8, 4>(&playfield, &Queue::parse("[OOOO]p4").unwrap(), pc, &config);
+        assert_eq!((result.solved, result.total), (1, 1));
+        assert_eq!(result.probability, 1.0);
+
+        let queue = Queue::parse("[IOT]p3").unwrap();
+        let result = coverage::<4, 8, 4>(&playfield, &queue, Goal::Survive(3), &config);
+        assert_eq!((result.solved, result.total), (6, 6));
+        assert_eq!(result.percentage(), 100.0);
+
+        // running out of budget decides nothing either way
+        for config in [
+            SearchConfig {
+                max_nodes: Some(1),
+                ..config
+            },
+            SearchConfig {
+                max_depth: 1,
+                ..config
+            },
+        ] {
+            let result = coverage::<4, 8, 4>(&playfield, &queue, Goal::Survive(3), &config);
+            assert_eq!((result.solved, result.undecided.len()), (0, 6));
+            assert!(result.failures.is_empty());
+        }
+
+        // the perfect clear solver keeps to the node budget as well
+        let config = SearchConfig {
+            max_nodes: Some(1),
+            ..config
+        };
+        let queue = Queue::parse("[OOOO]p4").unwrap();
+        let result = coverage::<4, 8, 4>(&playfield, &queue, pc, &config);
+        assert_eq!((result.solved, result.undecided.len()), (0, 1));
+    }
+
+    #[test]
+    fn playfield() {
+        let text = "\
+            OO..\n....\n....\nI...\n\
+            \n\
+            ....\n....\n....\nI...\n";
+        let playfield = parse_playfield::<4, 8, 4>(text).unwrap();
+
+        assert_eq!(playfield[0].bitboards[Tetromino::I as usize], 1);
+        assert_eq!(playfield[0].bitboards[Tetromino::O as usize], 0b11 << 24);
+        assert_eq!(playfield[1].occupied(), 1);
+        assert!(parse_playfield::<4, 8, 4>("OO.\n").is_err());
+    }
+}
@@ -0,0 +1,350 @@
+use std::collections::VecDeque;
+
+use crate::{action::Action, GameOutcome, GameState};
+
+/// Gravity is measured in fractions of a cell per frame, with this many fractions to a cell,
+/// so that it accumulates the same way on every machine.
+pub const GRAVITY_UNIT: u32 = 1 << 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GameConfig {
+    /// Cells the piece falls per frame, in units of [`GRAVITY_UNIT`].
+    pub gravity: u32,
+    /// Gravity while the soft drop is held, if it is stronger than the regular one.
+    pub soft_drop_gravity: u32,
+    /// Frames a resting piece waits before it locks.
+    pub lock_delay: u32,
+    /// Number of times moving or rotating a resting piece restarts its lock delay,
+    /// until the piece reaches a lower layer than before.
+    pub move_reset_limit: u32,
+    /// Frames a movement is held before it starts repeating.
+    pub das: u32,
+    /// Frames between repeated movements, where 0 moves the piece as far as it goes at once.
+    pub arr: u32,
+    /// Number of actions the state keeps to undo. Gravity alone performs one every few frames,
+    /// so keeping all of them would grow without bound.
+    pub undo_limit: usize,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            gravity: GRAVITY_UNIT / 60,
+            soft_drop_gravity: GRAVITY_UNIT,
+            lock_delay: 30,
+            move_reset_limit: 15,
+            das: 10,
+            arr: 2,
+            undo_limit: 0,
+        }
+    }
+}
+
+/// An action pressed or released at the start of a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Input {
+    pub frame: u64,
+    pub action: Action,
+    pub pressed: bool,
+}
+
+impl Input {
+    pub fn press(frame: u64, action: Action) -> Self {
+        Self {
+            frame,
+            action,
+            pressed: true,
+        }
+    }
+
+    pub fn release(frame: u64, action: Action) -> Self {
+        Self {
+            frame,
+            action,
+            pressed: false,
+        }
+    }
+}
+
+/// Plays a [`GameState`] in real time, one frame per [`Game::tick`].
+///
+/// Everything the game does follows from its initial state, its config and its inputs, so
+/// feeding [`Game::replay`] to a new game from the same state reproduces it exactly.
+#[derive(Debug, Clone)]
+pub struct Game<const W: usize, const H: usize, const D: usize> {
+    state: GameState<W, H, D>,
+    config: GameConfig,
+    frame: u64,
+    /// Inputs yet to be processed, in the order of their frames.
+    inputs: VecDeque<Input>,
+    replay: Vec<Input>,
+    /// The most recently pressed movement that is still held, along with the frames since.
+    held: Option<(Action, u32)>,
+    soft_dropping: bool,
+    /// Fractions of a cell the piece has yet to fall.
+    fall: u32,
+    lock_timer: u32,
+    resets: u32,
+    /// The lowest layer the current piece has reached.
+    lowest: isize,
+}
+
+impl<const W: usize, const H: usize, const D: usize> Game<W, H, D> {
+    pub fn new(state: GameState<W, H, D>, config: GameConfig) -> Self {
+        let lowest = state.piece.position[1];
+        Self {
+            state: state.with_undo_limit(config.undo_limit),
+            config,
+            frame: 0,
+            inputs: VecDeque::new(),
+            replay: Vec::new(),
+            held: None,
+            soft_dropping: false,
+            fall: 0,
+            lock_timer: 0,
+            resets: 0,
+            lowest,
+        }
+    }
+
+    pub fn state(&self) -> &GameState<W, H, D> {
+        &self.state
+    }
+
+    /// The frame the next tick plays.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Every input processed so far, stamped with the frame it was processed at.
+    pub fn replay(&self) -> &[Input] {
+        &self.replay
+    }
+
+    /// Queues `input` behind the inputs of the same frame. Inputs stamped with a frame that
+    /// has already been played are processed by the next tick.
+    pub fn push(&mut self, input: Input) {
+        let index = self
+            .inputs
+            .partition_point(|queued| queued.frame <= input.frame);
+        self.inputs.insert(index, input);
+    }
+
+    /// Plays `frames` frames, stopping early if the game ends.
+    pub fn run(&mut self, frames: u64) -> GameOutcome {
+        for _ in 0..frames {
+            if !self.tick().is_running() {
+                break;
+            }
+        }
+        self.state.outcome()
+    }
+
+    /// Plays one frame: processes its inputs, repeats the held movement,
+    /// lets the piece fall and locks it once its lock delay has run out.
+    pub fn tick(&mut self) -> GameOutcome {
+        if !self.state.outcome().is_running() {
+            return self.state.outcome();
+        }
+
+        while let Some(input) = self.inputs.front().copied() {
+            if input.frame > self.frame {
+                break;
+            }
+            self.inputs.pop_front();
+            self.input(Input {
+                frame: self.frame,
+                ..input
+            });
+        }
+
+        self.repeat();
+        self.fall();
+        self.settle();
+
+        self.frame += 1;
+        self.state.outcome()
+    }
+
+    fn input(&mut self, input: Input) {
+        self.replay.push(input);
+        let Input {
+            action, pressed, ..
+        } = input;
+
+        match action {
+            Action::SoftDrop => self.soft_dropping = pressed,
+            _ if action.is_movement() => {
+                if pressed {
+                    self.held = Some((action, 0));
+                    self.perform(action);
+                } else if self.held.is_some_and(|(held, _)| held == action) {
+                    self.held = None;
+                }
+            }
+            _ if pressed => {
+                self.perform(action);
+            }
+            _ => {}
+        }
+    }
+
+    fn repeat(&mut self) {
+        let Some((action, frames)) = self.held.as_mut() else {
+            return;
+        };
+        let action = *action;
+        *frames += 1;
+        let charged = match frames.checked_sub(self.config.das) {
+            None => return,
+            Some(charged) => charged,
+        };
+
+        if self.config.arr == 0 {
+            while self.perform(action) {}
+        } else if charged % self.config.arr == 0 {
+            self.perform(action);
+        }
+    }
+
+    fn fall(&mut self) {
+        let mut gravity = self.config.gravity;
+        if self.soft_dropping {
+            gravity = gravity.max(self.config.soft_drop_gravity);
+        }
+
+        self.fall = self.fall.saturating_add(gravity);
+        while self.fall >= GRAVITY_UNIT {
+            if !self.perform(Action::SoftDrop) {
+                // a resting piece does not build up a fall for when it moves off the ledge
+                self.fall = 0;
+                break;
+            }
+            self.fall -= GRAVITY_UNIT;
+        }
+
+        let y = self.state.piece.position[1];
+        if y < self.lowest {
+            self.lowest = y;
+            self.lock_timer = 0;
+            self.resets = 0;
+        }
+    }
+
+    fn settle(&mut self) {
+        if !self.is_resting() {
+            self.lock_timer = 0;
+            return;
+        }
+
+        self.lock_timer += 1;
+        if self.lock_timer >= self.config.lock_delay {
+            self.perform(Action::HardDrop);
+        }
+    }
+
+    fn perform(&mut self, action: Action) -> bool {
+        let performed = self.state.perform(action, true);
+        if !performed {
+            return false;
+        }
+
+        match action {
+            Action::HardDrop | Action::Hold => self.next_piece(),
+            Action::SoftDrop => {}
+            _ if self.lock_timer > 0 && self.resets < self.config.move_reset_limit => {
+                self.lock_timer = 0;
+                self.resets += 1;
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn next_piece(&mut self) {
+        self.fall = 0;
+        self.lock_timer = 0;
+        self.resets = 0;
+        self.lowest = self.state.piece.position[1];
+    }
+
+    fn is_resting(&self) -> bool {
+        let mut below = self.state.piece;
+        below.translate([0, -1, 0]);
+        !self.state.piece_fits(&below)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use queue::{Parsing, Queue};
+
+    use super::*;
+
+    #[test]
+    fn gravity_and_lock_delay() {
+        let config = GameConfig {
+            gravity: GRAVITY_UNIT / 2,
+            lock_delay: 3,
+            ..Default::default()
+        };
+        let state = GameState::<4, 8, 4>::from(Queue::parse("OO").unwrap());
+        let start = state.piece.position[1];
+        let mut game = Game::new(state, config);
+
+        // half a cell per frame
+        game.run(4);
+        assert_eq!(game.state().piece.position[1], start - 2);
+
+        // the piece falls to the floor and locks once it has rested there for the lock delay,
+        // counting the frame it lands in
+        let floor = game.state().dropped(game.state().piece).position[1];
+        let frames = 2 * (start - 2 - floor) as u64 + u64::from(config.lock_delay) - 1;
+        game.run(frames - 1);
+        assert_eq!(game.state().stats().pieces_placed, 0);
+        game.run(1);
+        assert_eq!(game.state().stats().pieces_placed, 1);
+        assert!(game.state().undo.is_empty());
+    }
+
+    #[test]
+    fn das_and_replay() {
+        let config = GameConfig {
+            gravity: 0,
+            das: 3,
+            arr: 0,
+            ..Default::default()
+        };
+        let state = GameState::<4, 8, 4>::from(Queue::parse("*p7").unwrap().with_seed(7));
+        let mut game = Game::new(state.clone(), config);
+
+        // tapping moves one cell, holding past the DAS moves all the way
+        game.push(Input::press(0, Action::MoveLeft));
+        game.push(Input::release(1, Action::MoveLeft));
+        game.run(2);
+        let mut expected = state.clone();
+        expected.perform(Action::MoveLeft, true);
+        assert_eq!(game.state().piece, expected.piece);
+
+        game.push(Input::press(2, Action::MoveRight));
+        game.run(2);
+        assert_eq!(game.state().piece, state.piece);
+        game.run(1);
+        while expected.perform(Action::MoveRight, true) {}
+        assert_eq!(game.state().piece, expected.piece);
+
+        game.push(Input::press(9, Action::HardDrop));
+        game.push(Input::press(9, Action::SoftDrop));
+        game.run(20);
+        assert_eq!(game.state().stats().pieces_placed, 1);
+        assert!(game.state().piece.position[1] < state.piece.position[1]);
+
+        let mut replayed = Game::new(state, config);
+        for &input in game.replay() {
+            replayed.push(input);
+        }
+        replayed.run(game.frame());
+        assert_eq!(replayed.state(), game.state());
+        assert_eq!(replayed.state().stats(), game.state().stats());
+    }
+}
@@ -1,19 +1,240 @@
-use crate::{action::Action, GameState};
+use crate::{GameOutcome, GameState};
+
+/// Scores game states of a `W`x`H`x`D` playfield, higher being better. The dimensions are
+/// parameters of the trait rather than of its method, so that it can be used as a trait object.
+pub trait Evaluator<const W: usize, const H: usize, const D: usize> {
+    fn evaluate(&self, game_state: &GameState<W, H, D>) -> f64;
+}
+
+/// Board features measured over the W×D grid of columns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Features<const W: usize, const D: usize> {
+    /// One above the highest occupied cell of each column, indexed by `[z][x]`.
+    pub heights: [[usize; W]; D],
+    pub aggregate_height: usize,
+    pub max_height: usize,
+    /// Empty cells covered by an occupied cell above them.
+    pub holes: usize,
+    /// Summed height differences between neighbouring columns along the X axis.
+    pub roughness_x: usize,
+    /// Summed height differences between neighbouring columns along the Z axis.
+    pub roughness_z: usize,
+    /// Summed depths of columns lower than all their neighbours, counting walls as neighbours.
+    pub well_depth: usize,
+    /// Sum of the squared fill ratios of the rows along both the X and the Z axis.
+    pub row_fill: f64,
+    /// Sum of the squared fill ratios of the layers.
+    pub layer_fill: f64,
+}
 
 impl<const W: usize, const H: usize, const D: usize> GameState<W, H, D> {
-    pub fn heuristic(&self) -> f64 {
-        let mut total = self
-            .history
-            .iter()
-            .filter(|&action| matches!(action, Action::HardDrop))
-            .count() as f64;
-
-        for i in shapes::MAX_SIZE..H {
-            if self.playfield[i].occupied() != 0 {
-                total = -1.0;
+    pub fn features(&self) -> Features<W, D> {
+        let is_filled = |x: usize, y: usize, z: usize| {
+            (self.playfield[y].occupied() >> (x + z * layer::MAX_SIZE)) & 1 == 1
+        };
+
+        let mut heights = [[0; W]; D];
+        let mut holes = 0;
+        for (z, row) in heights.iter_mut().enumerate() {
+            for (x, height) in row.iter_mut().enumerate() {
+                *height = (0..H)
+                    .rev()
+                    .find(|&y| is_filled(x, y, z))
+                    .map_or(0, |y| y + 1);
+                holes += (0..*height).filter(|&y| !is_filled(x, y, z)).count();
             }
         }
 
-        total
+        let mut roughness_x = 0;
+        let mut roughness_z = 0;
+        let mut well_depth = 0;
+        for z in 0..D {
+            for x in 0..W {
+                let height = heights[z][x];
+                if x + 1 < W {
+                    roughness_x += height.abs_diff(heights[z][x + 1]);
+                }
+                if z + 1 < D {
+                    roughness_z += height.abs_diff(heights[z + 1][x]);
+                }
+
+                let lowest_neighbour = [
+                    x.checked_sub(1).map(|x| heights[z][x]),
+                    (x + 1 < W).then(|| heights[z][x + 1]),
+                    z.checked_sub(1).map(|z| heights[z][x]),
+                    (z + 1 < D).then(|| heights[z + 1][x]),
+                ]
+                .into_iter()
+                .map(|neighbour| neighbour.unwrap_or(H))
+                .min()
+                .unwrap_or(H);
+                well_depth += lowest_neighbour.saturating_sub(height);
+            }
+        }
+
+        let mut row_fill = 0.0;
+        let mut layer_fill = 0.0;
+        for y in 0..H {
+            let mut filled = 0;
+            for z in 0..D {
+                let count = (0..W).filter(|&x| is_filled(x, y, z)).count();
+                row_fill += (count as f64 / W as f64).powi(2);
+                filled += count;
+            }
+            for x in 0..W {
+                let count = (0..D).filter(|&z| is_filled(x, y, z)).count();
+                row_fill += (count as f64 / D as f64).powi(2);
+            }
+            layer_fill += (filled as f64 / (W * D) as f64).powi(2);
+        }
+
+        Features {
+            heights,
+            aggregate_height: heights.iter().flatten().sum(),
+            max_height: heights.iter().flatten().copied().max().unwrap_or(0),
+            holes,
+            roughness_x,
+            roughness_z,
+            well_depth,
+            row_fill,
+            layer_fill,
+        }
+    }
+
+    /// Scores the state with the default [`Weights`].
+    pub fn heuristic(&self) -> f64 {
+        Weights::default().evaluate(self)
+    }
+}
+
+/// A linear evaluator with one weight per feature.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Weights {
+    pub pieces_placed: f64,
+    pub layers_cleared: f64,
+    pub aggregate_height: f64,
+    pub max_height: f64,
+    pub holes: f64,
+    pub roughness_x: f64,
+    pub roughness_z: f64,
+    pub well_depth: f64,
+    pub row_fill: f64,
+    pub layer_fill: f64,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self {
+            pieces_placed: 1.0,
+            layers_cleared: 2.0,
+            aggregate_height: -0.05,
+            max_height: -0.2,
+            holes: -0.8,
+            roughness_x: -0.1,
+            roughness_z: -0.1,
+            well_depth: -0.1,
+            row_fill: 0.1,
+            layer_fill: 0.5,
+        }
+    }
+}
+
+impl Weights {
+    pub const COUNT: usize = 10;
+
+    /// The weights in declaration order.
+    pub fn to_array(&self) -> [f64; Self::COUNT] {
+        [
+            self.pieces_placed,
+            self.layers_cleared,
+            self.aggregate_height,
+            self.max_height,
+            self.holes,
+            self.roughness_x,
+            self.roughness_z,
+            self.well_depth,
+            self.row_fill,
+            self.layer_fill,
+        ]
+    }
+
+    pub fn from_array(weights: [f64; Self::COUNT]) -> Self {
+        let [pieces_placed, layers_cleared, aggregate_height, max_height, holes, roughness_x, roughness_z, well_depth, row_fill, layer_fill] =
+            weights;
+        Self {
+            pieces_placed,
+            layers_cleared,
+            aggregate_height,
+            max_height,
+            holes,
+            roughness_x,
+            roughness_z,
+            well_depth,
+            row_fill,
+            layer_fill,
+        }
+    }
+}
+
+impl<const W: usize, const H: usize, const D: usize> Evaluator<W, H, D> for Weights {
+    fn evaluate(&self, game_state: &GameState<W, H, D>) -> f64 {
+        if game_state.outcome() == GameOutcome::ToppedOut {
+            return f64::NEG_INFINITY;
+        }
+
+        let features = game_state.features();
+        let stats = game_state.stats();
+
+        self.pieces_placed * stats.pieces_placed as f64
+            + self.layers_cleared * stats.layers_cleared as f64
+            + self.aggregate_height * features.aggregate_height as f64
+            + self.max_height * features.max_height as f64
+            + self.holes * features.holes as f64
+            + self.roughness_x * features.roughness_x as f64
+            + self.roughness_z * features.roughness_z as f64
+            + self.well_depth * features.well_depth as f64
+            + self.row_fill * features.row_fill
+            + self.layer_fill * features.layer_fill
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use layer::Layer;
+    use queue::{Parsing, Queue};
+    use tetrominos::Tetromino;
+
+    use super::*;
+
+    #[test]
+    fn features() {
+        // a column of height 3 at x = 0, z = 0 with a hole in the middle
+        let mut playfield = [Layer::default(); 8];
+        playfield[0].bitboards[Tetromino::D as usize] = 1;
+        playfield[2].bitboards[Tetromino::D as usize] = 1;
+        let game_state =
+            GameState::<4, 8, 4>::from(Queue::parse("[O]p1").unwrap()).with_playfield(playfield);
+
+        let features = game_state.features();
+        assert_eq!(features.heights[0], [3, 0, 0, 0]);
+        assert_eq!(features.aggregate_height, 3);
+        assert_eq!(features.max_height, 3);
+        assert_eq!(features.holes, 1);
+        assert_eq!((features.roughness_x, features.roughness_z), (3, 3));
+        // every empty column has an empty neighbour
+        assert_eq!(features.well_depth, 0);
+        assert_eq!(features.layer_fill, 2.0 * (1.0f64 / 16.0).powi(2));
+
+        let mut topped_out = game_state.clone();
+        topped_out.outcome = GameOutcome::ToppedOut;
+        assert!(game_state.heuristic() > topped_out.heuristic());
+
+        // evaluators can be picked at runtime
+        let evaluators: [Box<dyn Evaluator<4, 8, 4>>; 2] = [
+            Box::new(Weights::default()),
+            Box::new(Weights::from_array([0.0; Weights::COUNT])),
+        ];
+        let scores = evaluators.map(|evaluator| evaluator.evaluate(&game_state));
+        assert_eq!(scores, [game_state.heuristic(), 0.0]);
     }
 }
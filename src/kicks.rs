@@ -0,0 +1,307 @@
+use std::{fmt::Write, fs, io, path::Path, sync::Arc};
+
+use lazy_static::lazy_static;
+use orientation::{Axis, Direction, Orientation, Polarity, Rotation};
+use piece::Piece;
+use shapes::{BoundingBox, Shape, ShapeId};
+use strum::{EnumCount, IntoEnumIterator};
+use tetrominos::Tetromino;
+
+use crate::GameState;
+
+type Offset = [isize; Axis::COUNT];
+
+lazy_static! {
+    /// Shared by every game state that does not load a table of its own.
+    pub(crate) static ref DEFAULT_KICKS: Arc<KickTable> = Arc::new(KickTable::default());
+}
+
+/// The offsets a rotating piece tries in order, for every tetromino, orientation and rotation
+/// direction. The rotation succeeds with the first offset at which the rotated piece fits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KickTable {
+    /// Indexed by [`KickTable::index`].
+    kicks: Vec<Vec<Offset>>,
+}
+
+impl KickTable {
+    /// A table that never kicks, so rotations only succeed in place.
+    pub fn none() -> Self {
+        Self {
+            kicks: vec![vec![[0, 0, 0]]; ShapeId::COUNT * Direction::COUNT],
+        }
+    }
+
+    fn index(shape_id: ShapeId, around: Direction) -> usize {
+        around.combination() + shape_id.combination() * Direction::COUNT
+    }
+
+    pub fn kicks(&self, shape_id: ShapeId, around: Direction) -> &[Offset] {
+        &self.kicks[Self::index(shape_id, around)]
+    }
+
+    pub fn set(&mut self, shape_id: ShapeId, around: Direction, kicks: Vec<Offset>) {
+        self.kicks[Self::index(shape_id, around)] = kicks;
+    }
+
+    /// Writes the table in the format read by [`KickTable::parse`], one line per entry.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut text = String::new();
+        for shape_id in shape_ids() {
+            for around in directions() {
+                let ShapeId {
+                    variant,
+                    orientation,
+                } = shape_id;
+                let _ = write!(
+                    text,
+                    "{variant:?} {}{} {}",
+                    direction_name(orientation.direction),
+                    orientation.rotation as usize,
+                    direction_name(around)
+                );
+                for [x, y, z] in self.kicks(shape_id, around) {
+                    let _ = write!(text, " {x},{y},{z}");
+                }
+                text.push('\n');
+            }
+        }
+
+        fs::write(path, text)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Reads a table from lines of the form `T +y0 -x 0,0,0 1,0,0 0,1,0`: the tetromino, the
+    /// direction and rotation of its orientation, the direction it rotates around, then the
+    /// offsets to try. Any of the first three may be `*` to cover every value, and later lines
+    /// replace what earlier ones set. Entries that no line covers only rotate in place, and
+    /// `#` starts a comment.
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let mut table = Self::none();
+        for (number, line) in text.lines().enumerate() {
+            let invalid = |message: &str| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: {message}", number + 1),
+                )
+            };
+
+            let line = line.split('#').next().unwrap_or_default();
+            let mut tokens = line.split_whitespace();
+            let Some(variant) = tokens.next() else {
+                continue;
+            };
+            let (Some(orientation), Some(around)) = (tokens.next(), tokens.next()) else {
+                return Err(invalid(
+                    "expected a tetromino, an orientation and a direction",
+                ));
+            };
+
+            let variants: Vec<_> = match variant {
+                "*" => Tetromino::iter().collect(),
+                _ => vec![variant
+                    .parse()
+                    .map_err(|_| invalid(&format!("unknown tetromino `{variant}`")))?],
+            };
+            let orientations: Vec<_> = match orientation {
+                "*" => orientations().collect(),
+                _ => vec![parse_orientation(orientation)
+                    .ok_or_else(|| invalid(&format!("unknown orientation `{orientation}`")))?],
+            };
+            let arounds: Vec<_> = match around {
+                "*" => directions().collect(),
+                _ => vec![parse_direction(around)
+                    .ok_or_else(|| invalid(&format!("unknown direction `{around}`")))?],
+            };
+            let kicks = tokens
+                .map(|token| {
+                    parse_offset(token).ok_or_else(|| invalid(&format!("invalid offset `{token}`")))
+                })
+                .collect::<io::Result<Vec<_>>>()?;
+
+            for &variant in variants.iter() {
+                for &orientation in orientations.iter() {
+                    for &around in arounds.iter() {
+                        let shape_id = ShapeId::from((variant, orientation));
+                        table.set(shape_id, around, kicks.clone());
+                    }
+                }
+            }
+        }
+
+        Ok(table)
+    }
+}
+
+/// Analogous to SRS: after trying the rotation in place, the piece is nudged by one cell along
+/// each axis of the plane it rotates in, and then lifted by one cell. The I piece, which is longer
+/// than it is wide, may also be nudged by two.
+///
+/// Like in SRS, the offsets depend on the orientation: a piece rotates within a box of fixed
+/// size, which moves its cells within that box by an amount that differs from one orientation to
+/// the next. Along each axis the nudge that moves the cells back to where they were is tried first,
+/// and where they did not move, the one towards the side the piece rotates around.
+impl Default for KickTable {
+    fn default() -> Self {
+        let mut table = Self::none();
+        for shape_id in shape_ids() {
+            let before: &Shape = (&shape_id).into();
+            for around in directions() {
+                let after: &Shape = (&shape_id.rotate(around)).into();
+                let side = match around.polarity {
+                    Polarity::Positive => 1,
+                    Polarity::Negative => -1,
+                };
+                let distances: &[isize] = match shape_id.variant {
+                    Tetromino::I => &[1, 2],
+                    _ => &[1],
+                };
+
+                let mut kicks = vec![[0, 0, 0]];
+                for &distance in distances {
+                    for axis in Axis::iter().filter(|&axis| axis != around.axis) {
+                        let center = |shape: &Shape| {
+                            let BoundingBox { start, end } = shape.bounding_box;
+                            (start[axis as usize] + end[axis as usize]) as isize
+                        };
+                        let first = match (center(before) - center(after)).signum() {
+                            0 => side,
+                            drift => drift,
+                        };
+                        for sign in [first, -first] {
+                            let mut offset = [0; Axis::COUNT];
+                            offset[axis as usize] = sign * distance;
+                            kicks.push(offset);
+                        }
+                    }
+                }
+                if !kicks.contains(&[0, 1, 0]) {
+                    kicks.push([0, 1, 0]);
+                }
+
+                table.set(shape_id, around, kicks);
+            }
+        }
+        table
+    }
+}
+
+fn directions() -> impl Iterator<Item = Direction> {
+    Axis::iter().flat_map(|axis| Polarity::iter().map(move |polarity| (polarity, axis).into()))
+}
+
+fn orientations() -> impl Iterator<Item = Orientation> {
+    directions()
+        .flat_map(|direction| Rotation::iter().map(move |rotation| (direction, rotation).into()))
+}
+
+fn shape_ids() -> impl Iterator<Item = ShapeId> {
+    Tetromino::iter()
+        .flat_map(|variant| orientations().map(move |orientation| (variant, orientation).into()))
+}
+
+fn direction_name(direction: Direction) -> String {
+    let polarity = match direction.polarity {
+        Polarity::Positive => '+',
+        Polarity::Negative => '-',
+    };
+    let axis = match direction.axis {
+        Axis::X => 'x',
+        Axis::Y => 'y',
+        Axis::Z => 'z',
+    };
+    format!("{polarity}{axis}")
+}
+
+fn parse_direction(text: &str) -> Option<Direction> {
+    directions().find(|&direction| direction_name(direction) == text)
+}
+
+/// An orientation is written as its direction followed by the index of its rotation.
+fn parse_orientation(text: &str) -> Option<Orientation> {
+    let (direction, rotation) = text.split_at_checked(2)?;
+    let rotation: usize = rotation.parse().ok()?;
+    let rotation = Rotation::iter().nth(rotation)?;
+    Some((parse_direction(direction)?, rotation).into())
+}
+
+fn parse_offset(text: &str) -> Option<Offset> {
+    let coordinates = text
+        .split(',')
+        .map(|coordinate| coordinate.parse().ok())
+        .collect::<Option<Vec<isize>>>()?;
+    coordinates.try_into().ok()
+}
+
+impl<const W: usize, const H: usize, const D: usize> GameState<W, H, D> {
+    /// Rotates `piece` around `direction`, translated by the first offset of the kick table at
+    /// which it fits, and returns it along with the index of that offset.
+    pub(crate) fn kicked(&self, mut piece: Piece, direction: Direction) -> Option<(Piece, usize)> {
+        let shape_id = piece.shape_id;
+        piece.rotate(direction);
+
+        self.kicks
+            .kicks(shape_id, direction)
+            .iter()
+            .enumerate()
+            .find_map(|(index, &offset)| {
+                let mut kicked = piece;
+                kicked.translate(offset);
+                self.piece_fits(&kicked).then_some((kicked, index))
+            })
+    }
+
+    /// Rotates with `table` instead of the default kick table.
+    pub fn with_kick_table(mut self, table: KickTable) -> Self {
+        self.kicks = Arc::new(table);
+        self
+    }
+
+    /// Index of the kick the most recent action took, if it was a rotation.
+    pub fn last_kick(&self) -> Option<usize> {
+        self.last_kick
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use queue::{Parsing, Queue};
+
+    use super::*;
+    use crate::Action;
+
+    #[test]
+    fn wall_kick() {
+        let game_state = GameState::<4, 8, 4>::from(Queue::parse("I").unwrap());
+        let mut against_wall = game_state.clone();
+        while against_wall.perform(Action::MoveLeft, true) {}
+
+        // standing the I piece up along x needs room the wall does not leave
+        let direction = Direction::from((Polarity::Positive, Axis::Z));
+        let mut stuck = against_wall.clone().with_kick_table(KickTable::none());
+        assert!(!stuck.perform(Action::Rotate { direction }, true));
+
+        assert!(against_wall.perform(Action::Rotate { direction }, true));
+        assert!(against_wall.last_kick().is_some_and(|index| index > 0));
+        assert!(against_wall.perform(Action::SoftDrop, true));
+        assert_eq!(against_wall.last_kick(), None);
+
+        let table = KickTable::parse("* * * 0,0,0\nI +y0 +z 0,0,0 2,0,0 # nudge right\n").unwrap();
+        let shape_id = ShapeId::from(Tetromino::I);
+        assert_eq!(table.kicks(shape_id, direction), [[0, 0, 0], [2, 0, 0]]);
+        assert_eq!(table.kicks(shape_id, direction.opposite()), [[0, 0, 0]]);
+        assert!(KickTable::parse("I +y0 +w 0,0,0").is_err());
+
+        // rotating the O piece around x in its box moves its cells down and forwards,
+        // so it is nudged up and backwards first, unlike in its next orientation
+        let default = KickTable::default();
+        let around = Direction::from((Polarity::Positive, Axis::X));
+        let shape_id = ShapeId::from(Tetromino::O);
+        let kicks = [[0, 0, 0], [0, 1, 0], [0, -1, 0], [0, 0, -1], [0, 0, 1]];
+        assert_eq!(default.kicks(shape_id, around), kicks);
+        assert_ne!(default.kicks(shape_id.rotate(around), around), kicks);
+    }
+}
@@ -1,9 +1,8 @@
-use std::hash::Hash;
+use std::{collections::VecDeque, fmt, hash::Hash, sync::Arc};
 
-use action::Action;
 use colored::Colorize;
 use layer::Layer;
-use orientation::{Axis, Direction};
+use orientation::Axis;
 use piece::Piece;
 use queue::Queue;
 use shapes::{BoundingBox, Shape};
@@ -11,16 +10,41 @@ use strum::{EnumCount, IntoEnumIterator};
 use tetrominos::Tetromino;
 
 mod action;
-mod brute_forcing;
+pub mod brute_forcing;
+pub mod coverage;
+pub mod game;
 mod heuristic;
+mod kicks;
+mod outcome;
+pub mod pc_solver;
+mod placement;
+mod spin;
+mod stats;
+pub mod transposition;
+pub mod tuning;
+mod undo;
+mod zobrist;
+
+pub use action::Action;
+pub use heuristic::{Evaluator, Features, Weights};
+pub use kicks::KickTable;
+pub use outcome::GameOutcome;
+pub use placement::Placement;
+pub use spin::Spin;
+pub use stats::{ScoreTable, Stats};
 
 #[cfg(test)]
 mod tests {
 
+    use orientation::{Direction, Polarity};
     use queue::Parsing;
-    use std::{collections::HashSet, time::Instant};
+    use std::{
+        collections::HashSet,
+        time::{Duration, Instant},
+    };
 
     use super::*;
+    use crate::brute_forcing::SearchConfig;
 
     #[test]
     pub fn heuristic_testing() {
@@ -28,35 +52,259 @@ mod tests {
         game_state.perform(Action::HardDrop, true);
 
         game_state.perform(Action::HardDrop, true);
-        println!("{}", game_state.to_string());
+        println!("{game_state}");
         println!("{}", game_state.heuristic());
     }
 
+    #[test]
+    pub fn hold() {
+        let mut game_state = GameState::<4, 8, 4>::from(Queue::parse("[I]p1[O]p1[T]p1").unwrap());
+
+        assert!(game_state.perform(Action::Hold, true));
+        assert_eq!(game_state.piece.shape_id.variant, Tetromino::O);
+        assert_eq!(game_state.queue.hold, Some(Tetromino::I));
+        assert!(!game_state.perform(Action::Hold, true));
+
+        game_state.perform(Action::HardDrop, true);
+        assert_eq!(game_state.piece.shape_id.variant, Tetromino::T);
+
+        assert!(game_state.perform(Action::Hold, true));
+        assert_eq!(game_state.piece.shape_id.variant, Tetromino::I);
+        assert_eq!(game_state.queue.hold, Some(Tetromino::T));
+    }
+
+    #[test]
+    pub fn clear_non_adjacent_layers() {
+        // every cell of the 4x4 layers except the column at x = 2, z = 2 the I piece drops into
+        let full = 0x0F0F_0F0F;
+        let column = 1 << (2 + 2 * layer::MAX_SIZE);
+        let corner = 1;
+        let mut playfield = [Layer::default(); 8];
+        for (y, layer) in playfield.iter_mut().enumerate().take(4) {
+            let gaps = if y % 2 == 0 { column } else { column | corner };
+            layer.bitboards[Tetromino::D as usize] = full & !gaps;
+        }
+        let mut game_state = GameState::<4, 8, 4>::from(Queue::parse("[I]p1[I]p1").unwrap())
+            .with_playfield(playfield);
+
+        game_state.perform(Action::HardDrop, true);
+
+        assert_eq!(game_state.last_clear(), 2);
+        assert_eq!(game_state.cleared_layers(), 2);
+        for y in 0..2 {
+            assert_eq!(game_state.playfield[y].occupied(), full & !corner);
+        }
+        for y in 2..8 {
+            assert_eq!(game_state.playfield[y].occupied(), 0);
+        }
+    }
+
+    #[test]
+    pub fn scoring() {
+        let mut game_state = GameState::<4, 8, 4>::from(Queue::parse("[I]p1[I]p1[I]p1").unwrap())
+            .with_score_table(ScoreTable::default());
+
+        // two quads in a row, both of which are perfect clears
+        for _ in 0..2 {
+            let full = 0x0F0F_0F0F;
+            let column = 1 << (2 + 2 * layer::MAX_SIZE);
+            let mut playfield = game_state.playfield;
+            for layer in playfield.iter_mut().take(4) {
+                layer.bitboards[Tetromino::D as usize] = full & !column;
+            }
+            game_state = game_state.with_playfield(playfield);
+            game_state.perform(Action::HardDrop, true);
+        }
+
+        let stats = game_state.stats();
+        assert_eq!(stats.pieces_placed, 2);
+        assert_eq!(stats.layers_cleared, 8);
+        assert_eq!(stats.combo, 2);
+        assert!(stats.back_to_back);
+        assert_eq!(stats.perfect_clears, 2);
+        assert_eq!(stats.score, (800 + 3000) + (1200 + 50 + 3000));
+    }
+
+    #[test]
+    pub fn top_out() {
+        // a pillar under the column the I piece drops into keeps it above the ceiling
+        let column = 1 << (2 + 2 * layer::MAX_SIZE);
+        let mut playfield = [Layer::default(); 8];
+        for layer in playfield.iter_mut().take(4) {
+            layer.bitboards[Tetromino::D as usize] = column;
+        }
+        let mut game_state =
+            GameState::<4, 8, 4>::from(Queue::parse("[II]p2").unwrap()).with_playfield(playfield);
+
+        assert_eq!(game_state.hard_drop(), GameOutcome::ToppedOut);
+        assert!(!game_state.perform(Action::MoveLeft, true));
+    }
+
+    #[test]
+    pub fn queue_exhausted() {
+        let mut game_state = GameState::<4, 8, 4>::from(Queue::parse("[O]p1").unwrap());
+
+        assert!(!game_state.perform(Action::Hold, false));
+        assert_eq!(game_state.hard_drop(), GameOutcome::QueueExhausted);
+        assert_eq!(game_state.stats().pieces_placed, 1);
+    }
+
+    #[test]
+    pub fn placements() {
+        let game_state = GameState::<4, 8, 4>::from(Queue::parse("[O]p1").unwrap());
+
+        let mut cell_sets = HashSet::new();
+        for Placement { piece, actions, .. } in game_state.placements() {
+            let mut child = game_state.clone();
+            for action in actions {
+                assert!(child.perform(action, true));
+            }
+
+            let mut expected = game_state.clone();
+            expected.piece = piece;
+            expected.place_piece();
+            assert_eq!(child.playfield, expected.playfield);
+
+            assert!(cell_sets.insert(child.playfield));
+        }
+
+        // 9 flat positions on the floor and 12 upright ones along each horizontal axis
+        assert_eq!(cell_sets.len(), 33);
+    }
+
+    #[test]
+    pub fn equivalent_orientations() {
+        let game_state = GameState::<4, 8, 4>::from(Queue::parse("[O]p1").unwrap());
+
+        let mut rotated = game_state.clone();
+        let direction = Direction::from((Polarity::Positive, Axis::Y));
+        assert!(rotated.perform(Action::Rotate { direction }, true));
+        assert_ne!(rotated.piece, game_state.piece);
+
+        assert_eq!(rotated, game_state);
+        assert!(!HashSet::from([game_state.clone()]).insert(rotated));
+
+        // but a state that scored differently on the way is another one
+        let mut scored = game_state.clone();
+        scored.stats.score += 100;
+        assert_ne!(scored, game_state);
+        assert_ne!(scored.zobrist(), game_state.zobrist());
+    }
+
+    #[test]
+    pub fn incremental_zobrist() {
+        let full = 0x0F0F_0F0F;
+        let column = 1 << (2 + 2 * layer::MAX_SIZE);
+        let mut playfield = [Layer::default(); 8];
+        for (y, layer) in playfield.iter_mut().enumerate().take(3) {
+            let gaps = if y == 1 { column | 1 } else { column };
+            layer.bitboards[Tetromino::D as usize] = full & !gaps;
+        }
+        let mut game_state = GameState::<4, 8, 4>::from(Queue::parse("[I]p1[I]p1").unwrap())
+            .with_playfield(playfield);
+        assert_eq!(game_state.board_key, zobrist::layers(&playfield, 0));
+
+        let mut other = game_state.clone();
+        other.perform(Action::HardDrop, true);
+        assert_eq!(other.last_clear(), 2);
+        assert_eq!(other.board_key, zobrist::layers(&other.playfield, 0));
+
+        // the same state reached by a different path has the same key
+        game_state.perform(Action::MoveRight, true);
+        game_state.perform(Action::MoveLeft, true);
+        game_state.perform(Action::HardDrop, true);
+        assert_eq!(game_state, other);
+        assert_eq!(game_state.zobrist(), other.zobrist());
+
+        // so does a queue that yielded the same tetrominos in another order
+        let queue = Queue::parse("[IO]p2T").unwrap();
+        let (mut first, mut second) = (queue.clone(), queue.clone());
+        assert!(first.take(Tetromino::I) && first.take(Tetromino::O));
+        assert!(second.take(Tetromino::O) && second.take(Tetromino::I));
+        assert_eq!(first, second);
+        assert_eq!(zobrist::queue(&first), zobrist::queue(&second));
+        assert_ne!(zobrist::queue(&first), zobrist::queue(&queue));
+    }
+
     #[test]
     pub fn it_works() {
         let start = Instant::now();
         let game_state = GameState::<4, 8, 4>::default();
 
-        let mut map = HashSet::new();
-        let mut hashset_size = 0;
-        brute_forcing::algorithm(game_state, 200, &mut map, &mut hashset_size);
+        let config = SearchConfig {
+            max_depth: 200,
+            max_nodes: Some(500),
+            ..Default::default()
+        }
+        .with_time_limit(Duration::from_secs(30));
+        let result = brute_forcing::algorithm(game_state, &config);
 
-        println!("{}", map.iter().collect::<Vec<_>>().len());
+        println!(
+            "Explored {} states, every one up to depth {}",
+            result.nodes, result.depth
+        );
+        assert!(!result.complete);
+        assert!(result.nodes <= 500);
 
         println!("Program took: {:?}", start.elapsed());
     }
+
+    #[test]
+    pub fn complete_search() {
+        let game_state = GameState::<4, 8, 4>::from(Queue::parse("[OO]p2").unwrap());
+
+        let config = SearchConfig {
+            max_depth: 5,
+            ..Default::default()
+        };
+        let result = brute_forcing::algorithm(game_state.clone(), &config);
+
+        // the queue runs out after both pieces are placed
+        assert!(result.complete);
+        assert_eq!(result.depth, 2);
+        assert_eq!(result.best.unwrap().outcome(), GameOutcome::QueueExhausted);
+
+        // stopping at a depth the game goes on past leaves the search incomplete
+        for max_depth in [0, 1] {
+            let config = SearchConfig {
+                max_depth,
+                ..config
+            };
+            let result = brute_forcing::algorithm(game_state.clone(), &config);
+            let parallel = brute_forcing::parallel_algorithm(game_state.clone(), &config, 2);
+            assert!(!result.complete && !parallel.complete);
+            assert_eq!((result.depth, parallel.depth), (max_depth, max_depth));
+        }
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone)]
 pub struct GameState<const W: usize, const H: usize, const D: usize> {
     history: Vec<Action>,
     queue: Queue,
     piece: Piece,
     playfield: [Layer<W, D>; H],
+    last_clear: usize,
+    stats: Stats,
+    outcome: GameOutcome,
+    ceiling: usize,
+    /// Zobrist key of the occupied cells, kept up to date as pieces lock and layers clear.
+    board_key: u64,
+    queue_key: u64,
+    kicks: Arc<KickTable>,
+    /// Index of the kick taken by the most recent action, if it was a rotation.
+    last_kick: Option<usize>,
+    /// Spin of the most recent hard drop.
+    last_spin: Spin,
+    /// What the most recent actions of the history changed, to undo them.
+    undo: VecDeque<undo::Delta<W, D>>,
+    undo_limit: usize,
+    /// Undone actions, the most recently undone last.
+    redo: Vec<Action>,
 }
 
 impl<const W: usize, const H: usize, const D: usize> GameState<W, H, D> {
-    pub fn performable_actions(&mut self) -> Vec<Action> {
+    pub fn performable_actions(&self) -> Vec<Action> {
         // TODO: rotating more than twice is not necessary
         // TODO: just first try without softdropping
 
@@ -65,74 +313,216 @@ impl<const W: usize, const H: usize, const D: usize> GameState<W, H, D> {
             .filter(|&action| {
                 let last = 3;
                 let is_fine = if self.history.len() > last {
-                    self.history[self.history.len() - last..]
-                        .iter()
-                        .any(|&element| element == Action::HardDrop)
+                    self.history[self.history.len() - last..].contains(&Action::HardDrop)
                 } else {
                     true
                 };
 
-                self.perform(action, false) && is_fine
+                self.is_legal(action) && is_fine
             })
             .collect()
     }
 
+    /// Whether `action` can be performed, which is checked without changing the state or
+    /// allocating.
+    pub fn is_legal(&self, action: Action) -> bool {
+        if !self.outcome.is_running() {
+            return false;
+        }
+
+        match action {
+            Action::HardDrop => true,
+            Action::Hold => {
+                self.queue.can_swap && (self.queue.hold.is_some() || !self.queue.is_exhausted())
+            }
+            Action::Rotate { direction } => self.kicked(self.piece, direction).is_some(),
+            _ => self.fits(action.offset().expect("movements translate the piece")),
+        }
+    }
+
+    /// Performs `action` if it is legal, or only checks whether it is unless `should_perform`.
+    /// Whatever it performs can be taken back with [`GameState::undo`].
     pub fn perform(&mut self, action: Action, should_perform: bool) -> bool {
+        if !should_perform {
+            return self.is_legal(action);
+        }
+        if !self.outcome.is_running() {
+            return false;
+        }
+
         match action {
-            movement @ (Action::MoveForward
+            Action::MoveForward
             | Action::MoveBackward
             | Action::MoveLeft
-            | Action::MoveRight) => {
-                let offset = match movement {
-                    Action::MoveForward => [0, 0, 1],
-                    Action::MoveBackward => [0, 0, -1],
-                    Action::MoveLeft => [-1, 0, 0],
-                    Action::MoveRight => [1, 0, 0],
-                    _ => unreachable!(),
-                };
-
-                let fits = self.fits(offset, None);
-                if fits && should_perform {
-                    self.history.push(action);
-                    self.piece.translate(offset)
-                }
-                fits
-            }
-            Action::SoftDrop => {
-                let offset = [0, -1, 0];
-                let fits = self.fits(offset, None);
-                if fits && should_perform {
-                    self.history.push(action);
-                    self.piece.translate(offset);
+            | Action::MoveRight
+            | Action::SoftDrop => {
+                let offset = action.offset().expect("movements translate the piece");
+                if !self.fits(offset) {
+                    return false;
                 }
-                fits
+                self.history.push(action);
+                self.record(undo::Delta::Move {
+                    piece: self.piece,
+                    last_kick: self.last_kick,
+                });
+                self.piece.translate(offset);
+                self.last_kick = None;
             }
             Action::HardDrop => {
-                if should_perform {
-                    let offset = [0, -1, 0];
-                    while self.fits(offset, None) {
-                        self.piece.translate(offset);
-                    }
-                    self.place_piece();
-                    self.new_piece();
-                    self.clear_lines();
-                    self.history.push(action);
+                self.hard_drop();
+            }
+            Action::Hold => {
+                if !self.is_legal(action) {
+                    return false;
+                }
+                let delta = self.spawn_delta();
+                if let Some(variant) = self.queue.swap(self.piece.shape_id.variant) {
+                    self.spawn(variant);
                 }
-                true
+                self.history.push(action);
+                self.record(undo::Delta::Spawn(delta));
             }
             Action::Rotate { direction } => {
-                let fits = self.fits([0, 0, 0], Some(direction));
-                if fits && should_perform {
-                    self.piece.rotate(direction);
-                    self.history.push(action);
-                }
-                fits
+                let Some((piece, index)) = self.kicked(self.piece, direction) else {
+                    return false;
+                };
+                self.record(undo::Delta::Move {
+                    piece: self.piece,
+                    last_kick: self.last_kick,
+                });
+                self.piece = piece;
+                self.last_kick = Some(index);
+                self.history.push(action);
             }
         }
+
+        true
+    }
+
+    /// Drops the piece, locks it into the playfield and spawns the next one.
+    pub fn hard_drop(&mut self) -> GameOutcome {
+        if !self.outcome.is_running() {
+            return self.outcome;
+        }
+
+        self.lock();
+        if self.outcome.is_running() {
+            self.new_piece();
+        }
+
+        self.outcome
+    }
+
+    /// Drops the piece and locks it into the playfield without spawning the next one.
+    fn lock(&mut self) {
+        debug_assert_eq!(
+            self.board_key,
+            zobrist::layers(&self.playfield, 0),
+            "the playfield changed without its key, see `with_playfield`"
+        );
+        let mut delta = self.spawn_delta();
+        let dropped = self.dropped(self.piece);
+        // the piece spun into place only if the rotation was the last thing to move it
+        self.last_spin = match self.last_kick {
+            Some(_) if dropped == self.piece => self.spin(&self.piece),
+            _ => Spin::None,
+        };
+        self.piece = dropped;
+
+        let [_, y, _] = self.piece.position;
+        let [_, start_y, _] = self.piece.shape().bounding_box.start;
+        let locked_out = y + start_y as isize >= self.ceiling as isize;
+
+        self.place_piece();
+        delta.placed = Some(self.piece);
+        delta.cleared = (0..H)
+            .filter(|&y| self.playfield[y].is_full())
+            .map(|y| (y, self.playfield[y]))
+            .collect();
+        self.last_clear = self.clear_lines();
+        self.stats
+            .record(self.last_clear, self.is_empty(), self.last_spin);
+        self.queue.can_swap = true;
+        self.queue_key = zobrist::queue(&self.queue);
+        self.history.push(Action::HardDrop);
+        self.record(undo::Delta::Spawn(delta));
+
+        if locked_out {
+            self.outcome = GameOutcome::ToppedOut;
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.playfield.iter().all(|layer| layer.occupied() == 0)
+    }
+
+    pub fn outcome(&self) -> GameOutcome {
+        self.outcome
+    }
+
+    /// Sets the layer at and above which a locked piece tops out the game.
+    pub fn with_ceiling(mut self, ceiling: usize) -> Self {
+        self.ceiling = ceiling;
+        self
+    }
+
+    /// Number of layers cleared by the most recent hard drop.
+    pub fn last_clear(&self) -> usize {
+        self.last_clear
+    }
+
+    /// Spin of the most recent hard drop.
+    pub fn last_spin(&self) -> Spin {
+        self.last_spin
+    }
+
+    /// Number of layers cleared since the start of the game.
+    pub fn cleared_layers(&self) -> usize {
+        self.stats.layers_cleared
+    }
+
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    pub fn with_score_table(mut self, table: ScoreTable) -> Self {
+        self.stats.table = table;
+        self
+    }
+
+    /// Starts from `playfield` instead of an empty one.
+    pub fn with_playfield(mut self, playfield: [Layer<W, D>; H]) -> Self {
+        self.playfield = playfield;
+        self.board_key = zobrist::layers(&self.playfield, 0);
+        if self.outcome.is_running() && !self.fits([0, 0, 0]) {
+            self.outcome = GameOutcome::ToppedOut;
+        }
+        self
     }
 
     fn new_piece(&mut self) {
-        self.piece = Piece::from::<W, H, D>(self.queue.next())
+        match self.queue.try_next() {
+            Some(variant) => self.spawn(variant),
+            None => self.outcome = GameOutcome::QueueExhausted,
+        }
+    }
+
+    /// A key identifying the state up to equality, which is cheap to compute
+    /// regardless of the size of the playfield.
+    pub fn zobrist(&self) -> u64 {
+        self.board_key
+            ^ self.queue_key
+            ^ zobrist::piece(&self.piece)
+            ^ zobrist::stats(&self.stats, self.outcome, self.last_clear, self.last_spin)
+    }
+
+    fn spawn(&mut self, variant: Tetromino) {
+        self.queue_key = zobrist::queue(&self.queue);
+        self.piece = Piece::from::<W, H, D>(variant);
+        self.last_kick = None;
+        if !self.fits([0, 0, 0]) {
+            self.outcome = GameOutcome::ToppedOut;
+        }
     }
 
     fn translate(bitboard: u64, x: isize, z: isize) -> u64 {
@@ -146,15 +536,22 @@ impl<const W: usize, const H: usize, const D: usize> GameState<W, H, D> {
     }
 
     fn clear_lines(&mut self) -> usize {
-        let mut l = 0;
+        let Some(first) = self.playfield.iter().position(Layer::is_full) else {
+            return 0;
+        };
+        // every layer from the first cleared one upwards moves, so their keys are replaced
+        self.board_key ^= zobrist::layers(&self.playfield[first..], first);
+
+        let mut l = first;
         let mut cleared_layer_count = 0;
         while l < H - cleared_layer_count {
-            let layer = &mut self.playfield[l];
-            if layer.is_full() {
+            if self.playfield[l].is_full() {
                 cleared_layer_count += 1;
                 for i in l..H - cleared_layer_count {
                     self.playfield[i] = self.playfield[i + 1];
                 }
+                // the layer that moved into `l` has not been checked yet
+                continue;
             }
             l += 1;
         }
@@ -163,10 +560,7 @@ impl<const W: usize, const H: usize, const D: usize> GameState<W, H, D> {
             self.playfield[H - i - 1].clear();
         }
 
-        if cleared_layer_count >= 1 {
-            println!("{}", self.to_string());
-            panic!("")
-        }
+        self.board_key ^= zobrist::layers(&self.playfield[first..], first);
 
         cleared_layer_count
     }
@@ -182,9 +576,13 @@ impl<const W: usize, const H: usize, const D: usize> GameState<W, H, D> {
         let [_, start_y, _] = bounding_box.start;
         let [_, end_y, _] = bounding_box.end;
 
-        for i in start_y..=end_y {
-            self.playfield[(y + i as isize) as usize].bitboards
-                [self.piece.shape_id.variant as usize] |= Self::translate(bitboards[i], x, z);
+        let variant = self.piece.shape_id.variant;
+        for (i, &bitboard) in bitboards.iter().enumerate().take(end_y + 1).skip(start_y) {
+            let layer_y = (y + i as isize) as usize;
+            let cells = Self::translate(bitboard, x, z);
+
+            self.playfield[layer_y].bitboards[variant as usize] |= cells;
+            self.board_key ^= zobrist::bitboard_key(cells, layer_y, variant);
         }
     }
 
@@ -194,18 +592,18 @@ impl<const W: usize, const H: usize, const D: usize> GameState<W, H, D> {
         (x < 0 || y < 0 || z < 0) || (x >= W as isize || y >= H as isize || z >= D as isize)
     }
 
-    fn fits(&self, offset: [isize; Axis::COUNT], along: Option<Direction>) -> bool {
-        let Piece {
-            mut shape_id,
-            position,
-        } = self.piece;
-        if let Some(direction) = along {
-            shape_id = shape_id.rotate(direction);
-        }
+    fn fits(&self, offset: [isize; Axis::COUNT]) -> bool {
+        let mut piece = self.piece;
+        piece.translate(offset);
 
-        let [x, y, z] = position;
-        let [dx, dy, dz] = offset;
-        let [x, y, z] = [x + dx, y + dy, z + dz];
+        self.piece_fits(&piece)
+    }
+
+    fn piece_fits(&self, piece: &Piece) -> bool {
+        let &Piece {
+            shape_id,
+            position: [x, y, z],
+        } = piece;
 
         let shape: &Shape = (&shape_id).into();
         let &Shape {
@@ -228,10 +626,10 @@ impl<const W: usize, const H: usize, const D: usize> GameState<W, H, D> {
             return false;
         }
 
-        for i in start_y..=end_y {
+        for (i, &bitboard) in bitboards.iter().enumerate().take(end_y + 1).skip(start_y) {
             let layer = &self.playfield[(y + i as isize) as usize];
 
-            if layer.occupied() & Self::translate(bitboards[i], x, z) != 0 {
+            if layer.occupied() & Self::translate(bitboard, x, z) != 0 {
                 return false;
             }
         }
@@ -240,8 +638,8 @@ impl<const W: usize, const H: usize, const D: usize> GameState<W, H, D> {
     }
 }
 
-impl<const W: usize, const H: usize, const D: usize> ToString for GameState<W, H, D> {
-    fn to_string(&self) -> String {
+impl<const W: usize, const H: usize, const D: usize> fmt::Display for GameState<W, H, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut result = String::new();
 
         for z in (0..D).rev() {
@@ -265,34 +663,73 @@ impl<const W: usize, const H: usize, const D: usize> ToString for GameState<W, H
                 }
                 result.push_str("  ");
             }
-            result.push_str("\n");
+            result.push('\n');
+        }
+
+        if let Some(hold) = self.queue.hold {
+            result.push_str(&format!("hold {hold:?}  "));
+        }
+        if !self.queue.preview.is_empty() {
+            let preview: String = self
+                .queue
+                .preview
+                .iter()
+                .map(|variant| format!("{variant:?}"))
+                .collect();
+            result.push_str(&format!("next {preview}"));
         }
 
-        result
+        write!(f, "{result}")
     }
 }
 
 impl<const W: usize, const H: usize, const D: usize> From<Queue> for GameState<W, H, D> {
-    fn from(mut queue: Queue) -> Self {
-        let variant = queue.next();
-
-        let piece = Piece::from::<W, H, D>(variant);
-        let playfield = [Default::default(); H];
-
-        Self {
+    fn from(queue: Queue) -> Self {
+        let mut game_state = Self {
             history: Vec::new(),
             queue,
-            piece,
-            playfield,
-        }
+            // replaced by the first piece of the queue right below
+            piece: Piece::from::<W, H, D>(Tetromino::I),
+            playfield: [Default::default(); H],
+            last_clear: 0,
+            stats: Stats::default(),
+            outcome: GameOutcome::Running,
+            ceiling: H.saturating_sub(shapes::MAX_SIZE),
+            board_key: 0,
+            queue_key: 0,
+            kicks: kicks::DEFAULT_KICKS.clone(),
+            last_kick: None,
+            last_spin: Spin::None,
+            undo: VecDeque::new(),
+            undo_limit: usize::MAX,
+            redo: Vec::new(),
+        };
+        game_state.new_piece();
+
+        game_state
     }
 }
 
+/// Game states are equal when they lead to the same future and have scored the same on the way,
+/// so that a search ranking them by score can take one for the other. How they were reached and
+/// which of the equivalent orientations the piece is in do not matter.
+impl<const W: usize, const H: usize, const D: usize> PartialEq for GameState<W, H, D> {
+    fn eq(&self, other: &Self) -> bool {
+        self.queue == other.queue
+            && self.piece.canonical() == other.piece.canonical()
+            && self.playfield == other.playfield
+            && self.stats == other.stats
+            && self.outcome == other.outcome
+            && self.last_clear == other.last_clear
+            && self.last_spin == other.last_spin
+    }
+}
+
+impl<const W: usize, const H: usize, const D: usize> Eq for GameState<W, H, D> {}
+
 impl<const W: usize, const H: usize, const D: usize> Hash for GameState<W, H, D> {
     fn hash<T: std::hash::Hasher>(&self, state: &mut T) {
-        self.queue.hash(state);
-        self.piece.hash(state);
-        self.playfield.hash(state);
+        state.write_u64(self.zobrist());
     }
 }
 
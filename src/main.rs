@@ -0,0 +1,118 @@
+use std::{env, fs, process::ExitCode, time::Duration};
+
+use queue::Queue;
+use ternix::{
+    brute_forcing::SearchConfig,
+    coverage::{self, Goal},
+};
+use tetrominos::Tetromino;
+
+const USAGE: &str = "\
+usage: ternix coverage <queue> --goal <goal> [--field <file>] [--nodes <nodes>] [--time <seconds>]
+
+  <queue>    a queue pattern such as [IOTL]p3*p4
+  <goal>     pc, pc:<layers>, clear:<layers> or survive:<pieces>
+  <file>     the starting playfield, given layer by layer from the bottom up,
+             each as 4 lines of 4 cells (a tetromino letter or .) separated by empty lines
+  <nodes>    the number of states to explore at most for each order
+  <seconds>  the time to search for at most, after which the orders left are undecided";
+
+const WIDTH: usize = 4;
+const HEIGHT: usize = 8;
+const DEPTH: usize = 4;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("{message}\n\n{USAGE}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let Some((command, args)) = args.split_first() else {
+        return Err(String::from("Missing command"));
+    };
+    if command != "coverage" {
+        return Err(format!("Unknown command '{command}'"));
+    }
+
+    let mut queue = None;
+    let mut goal = None;
+    let mut playfield = Default::default();
+    let mut config = SearchConfig {
+        max_depth: usize::MAX,
+        ..Default::default()
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--goal" => {
+                let value = args.next().ok_or("Missing goal after --goal")?;
+                goal = Some(value.parse::<Goal>()?);
+            }
+            "--field" => {
+                let path = args.next().ok_or("Missing file after --field")?;
+                let text = fs::read_to_string(path)
+                    .map_err(|error| format!("Could not read '{path}': {error}"))?;
+                playfield = coverage::parse_playfield::<WIDTH, HEIGHT, DEPTH>(&text)?;
+            }
+            "--nodes" => {
+                let value = args.next().ok_or("Missing count after --nodes")?;
+                let nodes = value
+                    .parse()
+                    .map_err(|_| format!("Invalid count '{value}'"))?;
+                config.max_nodes = Some(nodes);
+            }
+            "--time" => {
+                let value = args.next().ok_or("Missing seconds after --time")?;
+                let seconds = value
+                    .parse()
+                    .map_err(|_| format!("Invalid seconds '{value}'"))?;
+                config = config.with_time_limit(Duration::from_secs_f64(seconds));
+            }
+            _ if queue.is_none() => {
+                queue = Some(arg.parse::<Queue>().map_err(|error| error.to_string())?)
+            }
+            _ => return Err(format!("Unexpected argument '{arg}'")),
+        }
+    }
+    let queue = queue.ok_or("Missing queue")?;
+    let goal = goal.ok_or("Missing goal")?;
+
+    let coverage = coverage::coverage::<WIDTH, HEIGHT, DEPTH>(&playfield, &queue, goal, &config);
+    println!(
+        "{}/{} orders achieve the goal ({:.2}%, {:.2}% of the draws)",
+        coverage.solved,
+        coverage.total,
+        coverage.percentage(),
+        100.0 * coverage.probability
+    );
+
+    print_orders("failing orders", &coverage.failures, &queue);
+    print_orders(
+        "orders the search ran out of budget on",
+        &coverage.undecided,
+        &queue,
+    );
+
+    Ok(())
+}
+
+/// Prints `orders` as the queues they were checked with, holding the piece `queue` holds.
+fn print_orders(title: &str, orders: &[Vec<Tetromino>], queue: &Queue) {
+    if orders.is_empty() {
+        return;
+    }
+
+    println!("{title}:");
+    for order in orders {
+        let mut fixed = Queue::fixed(order.clone());
+        fixed.hold = queue.hold;
+        println!("  {fixed}");
+    }
+}
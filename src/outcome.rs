@@ -0,0 +1,15 @@
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameOutcome {
+    #[default]
+    Running,
+    /// A piece spawned into the stack or locked entirely above the ceiling.
+    ToppedOut,
+    /// A finite queue ran out of pieces to spawn.
+    QueueExhausted,
+}
+
+impl GameOutcome {
+    pub fn is_running(&self) -> bool {
+        matches!(self, Self::Running)
+    }
+}
@@ -0,0 +1,347 @@
+use std::collections::HashSet;
+
+use queue::{Checkpoint, SavedMode};
+use tetrominos::Tetromino;
+
+use crate::{action::Action, brute_forcing::SearchConfig, zobrist, GameState, Placement};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PcConfig {
+    /// Number of layers the perfect clear has to fit into.
+    pub layers: usize,
+    /// Collect every solution instead of stopping at the first one.
+    pub find_all: bool,
+    pub use_hold: bool,
+}
+
+impl Default for PcConfig {
+    fn default() -> Self {
+        Self {
+            layers: 2,
+            find_all: false,
+            use_hold: true,
+        }
+    }
+}
+
+/// Finds the placement sequences that leave the playfield empty without ever building above
+/// `config.layers`. Whenever the queue could yield different tetrominos next, each of them is
+/// tried, so every solution records the pieces it was found for in its placements.
+pub fn solve<const W: usize, const H: usize, const D: usize>(
+    game_state: &GameState<W, H, D>,
+    config: &PcConfig,
+) -> Vec<Vec<Placement>> {
+    solve_within(game_state, config, &SearchConfig::default())
+        .expect("a search without a budget is never interrupted")
+}
+
+/// Like [`solve`], but gives up once the solver has tried `budget.max_nodes` states or reached
+/// `budget.deadline`, returning `None` unless it had already found the solution it was after.
+/// The depth of the search is bounded by the layers of the perfect clear, not by the budget.
+pub fn solve_within<const W: usize, const H: usize, const D: usize>(
+    game_state: &GameState<W, H, D>,
+    config: &PcConfig,
+    budget: &SearchConfig,
+) -> Option<Vec<Vec<Placement>>> {
+    let mut solver = Solver {
+        config,
+        budget,
+        nodes: 0,
+        interrupted: false,
+        path: Vec::new(),
+        solutions: Vec::new(),
+        dead_ends: HashSet::new(),
+    };
+    solver.search(&mut game_state.clone(), config.layers);
+
+    (!solver.interrupted).then_some(solver.solutions)
+}
+
+struct Solver<'a, const W: usize, const H: usize, const D: usize> {
+    config: &'a PcConfig,
+    budget: &'a SearchConfig,
+    nodes: usize,
+    interrupted: bool,
+    path: Vec<Placement>,
+    solutions: Vec<Vec<Placement>>,
+    /// States that are known not to lead to a perfect clear.
+    dead_ends: HashSet<GameState<W, H, D>>,
+}
+
+impl<const W: usize, const H: usize, const D: usize> Solver<'_, W, H, D> {
+    fn is_done(&self) -> bool {
+        self.interrupted || (!self.config.find_all && !self.solutions.is_empty())
+    }
+
+    /// Returns whether a solution was found below this state, with `layers` left to fill.
+    fn search(&mut self, game_state: &mut GameState<W, H, D>, layers: usize) -> bool {
+        if self.is_done() || !game_state.outcome().is_running() {
+            return false;
+        }
+        if !Self::is_feasible(game_state, layers) || self.dead_ends.contains(game_state) {
+            return false;
+        }
+        if self.budget.is_exhausted(self.nodes) {
+            self.interrupted = true;
+            return false;
+        }
+        self.nodes += 1;
+
+        let mut found = self.expand(game_state, None, layers);
+
+        // swapping a piece with an identical held one changes nothing
+        let current = game_state.piece.shape_id.variant;
+        if self.config.use_hold
+            && game_state.queue.can_swap
+            && game_state.queue.hold != Some(current)
+        {
+            let replacements = match game_state.queue.hold {
+                Some(held) => vec![held],
+                None => game_state.queue.candidates(),
+            };
+
+            for replacement in replacements {
+                let forced = game_state
+                    .queue
+                    .hold
+                    .is_none()
+                    .then(|| game_state.force_next(replacement));
+                game_state.make(&[Action::Hold]);
+                found |= self.expand(game_state, Some(Action::Hold), layers);
+                game_state.unmake(&[Action::Hold]);
+                if let Some(checkpoint) = forced {
+                    game_state.unforce(checkpoint);
+                }
+            }
+        }
+
+        // a search that was cut short may have missed a solution
+        if !found && !self.interrupted {
+            self.dead_ends.insert(game_state.snapshot());
+        }
+        found
+    }
+
+    fn expand(
+        &mut self,
+        game_state: &mut GameState<W, H, D>,
+        prefix: Option<Action>,
+        layers: usize,
+    ) -> bool {
+        let mut found = false;
+
+        // spins only matter for scoring, so every placement covering the same cells leaves the
+        // same board behind and only the first of them is searched
+        let mut boards = HashSet::new();
+        let placements = game_state.placements();
+        for placement in placements
+            .into_iter()
+            .filter(|placement| boards.insert(placement.piece.canonical()))
+        {
+            if self.is_done() {
+                break;
+            }
+
+            let mut actions: Vec<_> = prefix.into_iter().collect();
+            actions.extend(placement.actions.iter().copied());
+            self.path.push(Placement {
+                piece: placement.piece,
+                actions,
+                spin: placement.spin,
+            });
+
+            // each tetromino the queue could yield next is tried, or none once it has run out
+            let candidates = game_state.queue.candidates();
+            let nexts: Vec<_> = if candidates.is_empty() {
+                vec![None]
+            } else {
+                candidates.into_iter().map(Some).collect()
+            };
+            for next in nexts {
+                let forced = next.map(|variant| game_state.force_next(variant));
+                game_state.make(&placement.actions);
+
+                // the piece that comes next does not change whether this one cleared everything
+                let cleared = game_state.is_empty();
+                if cleared {
+                    self.solutions.push(self.path.clone());
+                    found = true;
+                } else if let Some(layers) = layers.checked_sub(game_state.last_clear()) {
+                    // clearing more layers than the perfect clear fits into leaves nothing to fill
+                    found |= self.search(game_state, layers);
+                }
+
+                game_state.unmake(&placement.actions);
+                if let Some(checkpoint) = forced {
+                    game_state.unforce(checkpoint);
+                }
+                if cleared {
+                    break;
+                }
+            }
+
+            self.path.pop();
+        }
+
+        found
+    }
+
+    /// Whether the remaining cells below `layers` can still be filled exactly by the pieces left.
+    fn is_feasible(game_state: &GameState<W, H, D>, layers: usize) -> bool {
+        if game_state.playfield[layers.min(H)..]
+            .iter()
+            .any(|layer| layer.occupied() != 0)
+        {
+            return false;
+        }
+
+        let filled: usize = game_state.playfield[..layers.min(H)]
+            .iter()
+            .map(|layer| layer.occupied().count_ones() as usize)
+            .sum();
+        let Some(missing) = (layers * W * D).checked_sub(filled) else {
+            return false;
+        };
+        if missing % 4 != 0 {
+            return false;
+        }
+
+        // the active piece and the held one are not part of the queue anymore
+        let available = game_state
+            .queue
+            .remaining()
+            .map(|remaining| remaining + 1 + game_state.queue.hold.is_some() as usize);
+
+        available.is_none_or(|available| missing / 4 <= available)
+    }
+}
+
+impl<const W: usize, const H: usize, const D: usize> GameState<W, H, D> {
+    /// Makes `variant`, which the queue has to be able to yield next, the next piece to spawn.
+    /// Returns what [`GameState::unforce`] needs to take it back.
+    fn force_next(&mut self, variant: Tetromino) -> Checkpoint<SavedMode> {
+        let checkpoint = self.queue.checkpoint();
+        let forced = self.queue.force(variant);
+        debug_assert!(forced, "{variant:?} is not a candidate of the queue");
+        self.queue_key = zobrist::queue(&self.queue);
+        checkpoint
+    }
+
+    fn unforce(&mut self, checkpoint: Checkpoint<SavedMode>) {
+        self.queue.restore(checkpoint);
+        self.queue_key = zobrist::queue(&self.queue);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use layer::Layer;
+    use queue::{Parsing, Queue};
+
+    use super::*;
+
+    fn replay(game_state: &GameState<4, 8, 4>, solution: &[Placement]) -> GameState<4, 8, 4> {
+        let mut game_state = game_state.clone();
+        for placement in solution {
+            if placement.actions.first() == Some(&Action::Hold) {
+                game_state.perform(Action::Hold, true);
+            }
+            game_state.piece = placement.piece;
+            game_state.lock();
+        }
+        game_state
+    }
+
+    #[test]
+    fn single_layer() {
+        let game_state = GameState::<4, 8, 4>::from(Queue::parse("[OOOO]p4").unwrap());
+        let config = PcConfig {
+            layers: 1,
+            ..Default::default()
+        };
+
+        let solutions = solve(&game_state, &config);
+        assert_eq!(solutions.len(), 1);
+        assert_eq!(solutions[0].len(), 4);
+        assert!(replay(&game_state, &solutions[0]).is_empty());
+    }
+
+    #[test]
+    fn all_solutions() {
+        let game_state = GameState::<4, 8, 4>::from(Queue::parse("[IIOO]p4").unwrap());
+        let config = PcConfig {
+            layers: 1,
+            find_all: true,
+            use_hold: false,
+        };
+
+        let solutions = solve(&game_state, &config);
+        assert!(solutions.len() > 1);
+        for solution in solutions.iter() {
+            assert_eq!(solution.len(), 4);
+            assert!(replay(&game_state, solution).is_empty());
+        }
+
+        // the I pieces fill two rows (or columns) side by side or along opposite edges, leaving a
+        // band two cells wide for the O pieces
+        let rows = |first: usize, second: usize| {
+            0b1111 << (first * layer::MAX_SIZE) | 0b1111 << (second * layer::MAX_SIZE)
+        };
+        let columns = |first: usize, second: usize| {
+            (0..4).fold(0, |mask, z| {
+                mask | (1 << first | 1 << second) << (z * layer::MAX_SIZE)
+            })
+        };
+        let expected: HashSet<u64> = [(0, 1), (2, 3), (0, 3)]
+            .into_iter()
+            .flat_map(|(first, second)| [rows(first, second), columns(first, second)])
+            .collect();
+
+        let layouts: HashSet<u64> = solutions
+            .iter()
+            .map(|solution| {
+                let mut lines = game_state.clone();
+                for placement in solution {
+                    if placement.piece.shape_id.variant == Tetromino::I {
+                        lines.piece = placement.piece;
+                        lines.place_piece();
+                    }
+                }
+                lines.playfield[0].bitboards[Tetromino::I as usize]
+            })
+            .collect();
+        assert_eq!(layouts, expected);
+    }
+
+    #[test]
+    fn budget() {
+        let game_state = GameState::<4, 8, 4>::from(Queue::parse("[OOOO]p4").unwrap());
+        let config = PcConfig {
+            layers: 1,
+            ..Default::default()
+        };
+
+        let budget = |max_nodes| SearchConfig {
+            max_nodes: Some(max_nodes),
+            ..Default::default()
+        };
+        assert_eq!(solve_within(&game_state, &config, &budget(2)), None);
+        let solutions = solve_within(&game_state, &config, &budget(1000)).unwrap();
+        assert_eq!(solutions, solve(&game_state, &config));
+    }
+
+    #[test]
+    fn parity() {
+        let mut playfield = [Layer::default(); 8];
+        playfield[0].bitboards[Tetromino::D as usize] = 1;
+        let game_state =
+            GameState::<4, 8, 4>::from(Queue::parse("[OOOO]p4").unwrap()).with_playfield(playfield);
+
+        let config = PcConfig {
+            layers: 1,
+            ..Default::default()
+        };
+
+        assert!(solve(&game_state, &config).is_empty());
+    }
+}
@@ -0,0 +1,202 @@
+use std::cell::RefCell;
+
+use piece::Piece;
+use shapes::ShapeId;
+use strum::EnumCount;
+
+use crate::{action::Action, GameState, Spin};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placement {
+    /// The piece where it locks into the playfield.
+    pub piece: Piece,
+    /// The shortest sequence of actions that locks the piece there, ending with a hard drop.
+    pub actions: Vec<Action>,
+    /// Whether the piece spins into place, which the actions are chosen to preserve.
+    pub spin: Spin,
+}
+
+/// A piece the flood fill reached, and whether it got there by a rotation.
+type Reached = (Piece, bool);
+
+/// The buffers the flood fill works in, kept between calls so that enumerating placements does
+/// not allocate more than the placements it returns.
+#[derive(Default)]
+struct Scratch {
+    visited: Bits,
+    landed: Bits,
+    /// The frontier in the order it is explored, each with the action from its parent, so that
+    /// only the paths to placements are ever collected.
+    frontier: Vec<(Reached, Option<(usize, Action)>)>,
+    moves: Vec<Action>,
+}
+
+thread_local! {
+    static SCRATCH: RefCell<Scratch> = RefCell::default();
+}
+
+/// Bits that are all clear between uses, along with the words that were set since,
+/// so that clearing them does not go over the whole buffer.
+#[derive(Default)]
+struct Bits {
+    words: Vec<u64>,
+    touched: Vec<usize>,
+}
+
+/// A set of pieces that fit a `W`x`H`x`D` playfield, each with one of `tags` tags,
+/// stored as one bit per piece and tag so that the flood fill does not hash.
+/// It clears the bits it borrows once dropped.
+struct PieceSet<'a, const W: usize, const H: usize, const D: usize> {
+    bits: &'a mut Bits,
+    tags: usize,
+}
+
+impl<'a, const W: usize, const H: usize, const D: usize> PieceSet<'a, W, H, D> {
+    /// The bounding box of a shape may start past the position of its piece, so the position
+    /// of a piece that fits can lie up to its size below the playfield along every axis.
+    const MARGIN: usize = shapes::MAX_SIZE;
+
+    fn new(bits: &'a mut Bits, tags: usize) -> Self {
+        let positions = (W + Self::MARGIN) * (H + Self::MARGIN) * (D + Self::MARGIN);
+        let words = (ShapeId::COUNT * tags * positions).div_ceil(u64::BITS as usize);
+        if bits.words.len() < words {
+            bits.words.resize(words, 0);
+        }
+        Self { bits, tags }
+    }
+
+    /// Whether `piece` was not in the set with `tag` yet.
+    fn insert(&mut self, piece: &Piece, tag: usize) -> bool {
+        let [x, y, z] = piece
+            .position
+            .map(|coordinate| (coordinate + Self::MARGIN as isize) as usize);
+        let index = piece.shape_id.combination() * self.tags + tag;
+        let index =
+            ((index * (W + Self::MARGIN) + x) * (H + Self::MARGIN) + y) * (D + Self::MARGIN) + z;
+
+        let (word, bit) = (
+            index / u64::BITS as usize,
+            1 << (index % u64::BITS as usize),
+        );
+        let words = &mut self.bits.words;
+        if words[word] == 0 {
+            self.bits.touched.push(word);
+        }
+        let inserted = words[word] & bit == 0;
+        words[word] |= bit;
+        inserted
+    }
+}
+
+impl<const W: usize, const H: usize, const D: usize> Drop for PieceSet<'_, W, H, D> {
+    fn drop(&mut self) {
+        let Bits { words, touched } = &mut *self.bits;
+        for word in touched.drain(..) {
+            words[word] = 0;
+        }
+    }
+}
+
+impl<const W: usize, const H: usize, const D: usize> GameState<W, H, D> {
+    /// Enumerates every distinct set of cells the current piece can lock into, once for each
+    /// spin it can lock there with, by flood filling over the piece positions and orientations
+    /// reachable from the spawn.
+    pub fn placements(&self) -> Vec<Placement> {
+        if !self.outcome.is_running() {
+            return Vec::new();
+        }
+
+        SCRATCH.with_borrow_mut(|scratch| self.flood_fill(scratch))
+    }
+
+    fn flood_fill(&self, scratch: &mut Scratch) -> Vec<Placement> {
+        let Scratch {
+            visited,
+            landed,
+            frontier,
+            moves,
+        } = scratch;
+        if moves.is_empty() {
+            moves.extend(Action::get_all_actions().into_iter().filter(|action| {
+                action.offset().is_some() || matches!(action, Action::Rotate { .. })
+            }));
+        }
+
+        let mut placements = Vec::new();
+        // whether the piece got somewhere by a rotation matters for the spin it locks with
+        let start = (self.piece, self.last_kick.is_some());
+        let mut visited = PieceSet::<W, H, D>::new(visited, 2);
+        visited.insert(&start.0, usize::from(start.1));
+        let mut landed = PieceSet::<W, H, D>::new(landed, 3);
+        frontier.clear();
+        frontier.push((start, None));
+
+        let mut next_index = 0;
+        while let Some(&((piece, rotated), _)) = frontier.get(next_index) {
+            let index = next_index;
+            next_index += 1;
+
+            let dropped = self.dropped(piece);
+            let spin = if rotated && dropped == piece {
+                self.spin(&piece)
+            } else {
+                Spin::None
+            };
+            if landed.insert(&dropped.canonical(), spin as usize) {
+                let mut actions = vec![Action::HardDrop];
+                let mut current = index;
+                while let Some((parent, action)) = frontier[current].1 {
+                    actions.push(action);
+                    current = parent;
+                }
+                actions.reverse();
+                placements.push(Placement {
+                    piece: dropped,
+                    actions,
+                    spin,
+                });
+            }
+
+            for &action in moves.iter() {
+                let next = match action {
+                    Action::Rotate { direction } => {
+                        self.kicked(piece, direction).map(|(next, _)| next)
+                    }
+                    _ => {
+                        let mut next = piece;
+                        next.translate(action.offset().expect("only movements are left"));
+                        self.piece_fits(&next).then_some(next)
+                    }
+                };
+
+                let Some(next) = next else {
+                    continue;
+                };
+                // a rotation only matters while the piece rests, as dropping it undoes the spin
+                let rotated = matches!(action, Action::Rotate { .. }) && {
+                    let mut below = next;
+                    below.translate([0, -1, 0]);
+                    !self.piece_fits(&below)
+                };
+                let next = (next, rotated);
+                if visited.insert(&next.0, usize::from(next.1)) {
+                    frontier.push((next, Some((index, action))));
+                }
+            }
+        }
+
+        placements
+    }
+
+    pub(crate) fn dropped(&self, mut piece: Piece) -> Piece {
+        let offset = [0, -1, 0];
+        loop {
+            let mut next = piece;
+            next.translate(offset);
+            if !self.piece_fits(&next) {
+                return piece;
+            }
+            piece = next;
+        }
+    }
+}
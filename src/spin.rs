@@ -0,0 +1,155 @@
+use orientation::{Axis, Direction, Polarity};
+use piece::Piece;
+use shapes::BoundingBox;
+use strum::{EnumCount, IntoEnumIterator};
+
+use crate::GameState;
+
+/// How a piece that rotated into the place it locks at is classified.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Spin {
+    #[default]
+    None,
+    Mini,
+    Full,
+}
+
+impl Spin {
+    pub fn is_spin(&self) -> bool {
+        !matches!(self, Self::None)
+    }
+}
+
+impl<const W: usize, const H: usize, const D: usize> GameState<W, H, D> {
+    /// Classifies `piece` locking where it is, given that it got there by a rotation.
+    ///
+    /// A piece that could still move in any of the six directions did not spin. Otherwise the
+    /// corners of its bounding box that it does not fill itself decide, like the corners around
+    /// the T piece do in two dimensions: it is a full spin if at least three quarters of them
+    /// are blocked by the stack or the walls, and a mini spin if not.
+    pub(crate) fn spin(&self, piece: &Piece) -> Spin {
+        let mobile = Axis::iter().any(|axis| {
+            Polarity::iter().any(|polarity| {
+                let mut moved = *piece;
+                moved.translate(Self::unit(Direction::from((polarity, axis))));
+                self.piece_fits(&moved)
+            })
+        });
+        if mobile {
+            return Spin::None;
+        }
+
+        let corners = Self::corners(piece);
+        let blocked = corners
+            .iter()
+            .filter(|&&corner| self.is_blocked(corner))
+            .count();
+        if !corners.is_empty() && 4 * blocked >= 3 * corners.len() {
+            Spin::Full
+        } else {
+            Spin::Mini
+        }
+    }
+
+    fn unit(direction: Direction) -> [isize; Axis::COUNT] {
+        let mut offset = [0; Axis::COUNT];
+        offset[direction.axis as usize] = match direction.polarity {
+            Polarity::Positive => 1,
+            Polarity::Negative => -1,
+        };
+        offset
+    }
+
+    /// The distinct corners of the bounding box of `piece` that it does not fill, in playfield
+    /// coordinates. Boxes that are flat along an axis have fewer than eight corners.
+    fn corners(piece: &Piece) -> Vec<[isize; Axis::COUNT]> {
+        let shape = piece.shape();
+        let BoundingBox { start, end } = shape.bounding_box;
+
+        let mut corners = Vec::new();
+        for index in 0..1 << Axis::COUNT {
+            let corner: [usize; Axis::COUNT] = std::array::from_fn(|axis| {
+                if index >> axis & 1 == 0 {
+                    start[axis]
+                } else {
+                    end[axis]
+                }
+            });
+            let [x, y, z] = corner;
+            let filled = shape.bitboards[y] >> (x + z * layer::MAX_SIZE) & 1 == 1;
+            if filled || corners.contains(&corner) {
+                continue;
+            }
+            corners.push(corner);
+        }
+
+        corners
+            .into_iter()
+            .map(|corner| std::array::from_fn(|axis| piece.position[axis] + corner[axis] as isize))
+            .collect()
+    }
+
+    fn is_blocked(&self, cell: [isize; Axis::COUNT]) -> bool {
+        if Self::out_of_bounds(cell) {
+            return true;
+        }
+
+        let [x, y, z] = cell.map(|coordinate| coordinate as usize);
+        self.playfield[y].occupied() >> (x + z * layer::MAX_SIZE) & 1 == 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use queue::{Parsing, Queue};
+    use tetrominos::Tetromino;
+
+    use super::*;
+    use crate::{Action, ScoreTable};
+
+    #[test]
+    fn spins() {
+        let mut game_state = GameState::<4, 8, 4>::from(Queue::parse("TT").unwrap())
+            .with_score_table(ScoreTable::default());
+        let piece = game_state.dropped(game_state.piece);
+
+        // on the bare floor the piece can still move up and sideways
+        assert_eq!(game_state.spin(&piece), Spin::None);
+
+        // a stack that encloses the piece everywhere, without completing a layer
+        let shape = piece.shape();
+        let [x, y, z] = piece.position;
+        let mut playfield = game_state.playfield;
+        for (i, layer) in playfield.iter_mut().enumerate().take(4) {
+            let cells = match i.checked_sub(y as usize) {
+                Some(i) if i < shapes::MAX_SIZE => {
+                    GameState::<4, 8, 4>::translate(shape.bitboards[i], x, z)
+                }
+                _ => 0,
+            };
+            layer.bitboards[Tetromino::D as usize] = 0x0F0F_0F0F & !cells & !(1 << 3);
+        }
+        game_state = game_state.with_playfield(playfield);
+        assert_eq!(game_state.spin(&piece), Spin::Full);
+
+        // the T stands upright on the floor, so the two cells beside its stem are the corners
+        let corners = GameState::<4, 8, 4>::corners(&piece);
+        assert_eq!(corners, [[0, 1, 1], [2, 1, 1]]);
+
+        // with one of them free the piece still cannot move up, as the other one holds it
+        playfield[1].bitboards[Tetromino::D as usize] &= !(1 << layer::MAX_SIZE);
+        game_state = game_state.with_playfield(playfield);
+        assert_eq!(game_state.spin(&piece), Spin::Mini);
+
+        // the spin counts once the piece locks after rotating into place
+        game_state.piece = piece;
+        game_state.last_kick = Some(0);
+        assert!(game_state.perform(Action::HardDrop, true));
+        assert_eq!(game_state.last_spin(), Spin::Mini);
+        assert_eq!(game_state.stats().spins, 1);
+        assert_eq!(
+            game_state.stats().score,
+            ScoreTable::default().mini_spins[0]
+        );
+    }
+}
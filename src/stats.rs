@@ -0,0 +1,144 @@
+use crate::Spin;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ScoreTable {
+    /// Points awarded for clearing the indexed number of layers with a single drop.
+    pub clears: [u64; shapes::MAX_SIZE + 1],
+    /// Points awarded instead of [`ScoreTable::clears`] when the drop was a full spin.
+    pub spins: [u64; shapes::MAX_SIZE + 1],
+    /// Points awarded instead of [`ScoreTable::clears`] when the drop was a mini spin.
+    pub mini_spins: [u64; shapes::MAX_SIZE + 1],
+    /// Points awarded per consecutive clearing drop after the first one.
+    pub combo: u64,
+    /// Bonus in percent on top of a difficult clear that follows another difficult clear.
+    pub back_to_back: u64,
+    pub perfect_clear: u64,
+}
+
+impl ScoreTable {
+    /// A clear is difficult if it clears as many layers as the tallest piece can span,
+    /// or if it is a spin.
+    pub fn is_difficult(&self, cleared: usize, spin: Spin) -> bool {
+        cleared == shapes::MAX_SIZE || (cleared > 0 && spin.is_spin())
+    }
+
+    pub fn points(&self, cleared: usize, spin: Spin) -> u64 {
+        match spin {
+            Spin::None => self.clears[cleared],
+            Spin::Mini => self.mini_spins[cleared],
+            Spin::Full => self.spins[cleared],
+        }
+    }
+}
+
+impl Default for ScoreTable {
+    fn default() -> Self {
+        Self {
+            clears: [0, 100, 300, 500, 800],
+            spins: [400, 800, 1200, 1600, 2000],
+            mini_spins: [100, 200, 400, 600, 800],
+            combo: 50,
+            back_to_back: 50,
+            perfect_clear: 3000,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Stats {
+    pub table: ScoreTable,
+    pub pieces_placed: usize,
+    pub layers_cleared: usize,
+    /// Number of consecutive drops that cleared at least one layer.
+    pub combo: usize,
+    pub back_to_back: bool,
+    pub perfect_clears: usize,
+    /// Number of drops that were full or mini spins.
+    pub spins: usize,
+    pub score: u64,
+}
+
+impl Stats {
+    pub fn new(table: ScoreTable) -> Self {
+        Self {
+            table,
+            ..Default::default()
+        }
+    }
+
+    pub fn record(&mut self, cleared: usize, perfect_clear: bool, spin: Spin) {
+        self.pieces_placed += 1;
+        self.layers_cleared += cleared;
+        if spin.is_spin() {
+            self.spins += 1;
+        }
+
+        let mut points = self.table.points(cleared, spin);
+        if cleared == 0 {
+            self.combo = 0;
+            self.score += points;
+            return;
+        }
+
+        let difficult = self.table.is_difficult(cleared, spin);
+        if difficult && self.back_to_back {
+            points += points * self.table.back_to_back / 100;
+        }
+        self.back_to_back = difficult;
+
+        points += self.table.combo * self.combo as u64;
+        self.combo += 1;
+
+        if perfect_clear {
+            self.perfect_clears += 1;
+            points += self.table.perfect_clear;
+        }
+
+        self.score += points;
+    }
+
+    /// What [`Stats::record`] overwrites instead of counting up.
+    pub(crate) fn streak(&self) -> Streak {
+        Streak {
+            combo: self.combo,
+            back_to_back: self.back_to_back,
+            score: self.score,
+        }
+    }
+
+    /// Takes back the most recent [`Stats::record`], given the same arguments
+    /// and the streak from before it.
+    pub(crate) fn unrecord(
+        &mut self,
+        cleared: usize,
+        perfect_clear: bool,
+        spin: Spin,
+        streak: Streak,
+    ) {
+        self.pieces_placed -= 1;
+        self.layers_cleared -= cleared;
+        if spin.is_spin() {
+            self.spins -= 1;
+        }
+        if cleared > 0 && perfect_clear {
+            self.perfect_clears -= 1;
+        }
+
+        let Streak {
+            combo,
+            back_to_back,
+            score,
+        } = streak;
+        self.combo = combo;
+        self.back_to_back = back_to_back;
+        self.score = score;
+    }
+}
+
+/// The stats that depend on the drops before the most recent one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Streak {
+    combo: usize,
+    back_to_back: bool,
+    score: u64,
+}
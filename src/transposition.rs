@@ -0,0 +1,137 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A fixed size hash table keyed by zobrist keys, which replaces entries on collision
+/// so that its memory usage never grows.
+///
+/// Entries are told apart by their full 64-bit key alone, so two states with the same key are
+/// taken for the same state. Among the states of one search that is as unlikely as for random
+/// keys, but the key of a queue only tells apart the queues a single queue turns into, so a table
+/// is meant to be filled by searches from one root and cleared before searching from another.
+#[derive(Debug, Clone)]
+pub struct TranspositionTable<T> {
+    entries: Vec<Option<(u64, T)>>,
+    mask: usize,
+}
+
+impl<T: Copy> TranspositionTable<T> {
+    /// Creates a table with room for `size` entries, rounded up to a power of two.
+    pub fn new(size: usize) -> Self {
+        let size = size.max(1).next_power_of_two();
+
+        Self {
+            entries: vec![None; size],
+            mask: size - 1,
+        }
+    }
+
+    pub fn get(&self, key: u64) -> Option<T> {
+        match self.entries[key as usize & self.mask] {
+            Some((stored, value)) if stored == key => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn insert(&mut self, key: u64, value: T) {
+        self.entries[key as usize & self.mask] = Some((key, value));
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.fill(None);
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+/// A transposition table of small values that can be shared between threads without locking,
+/// which keeps the smallest value inserted for each key.
+///
+/// Each slot packs an entry into a single word, the value taking the low bits of the key, and
+/// keeps the smaller of the stored and the inserted word. Its contents therefore do not depend on
+/// the order concurrent inserts happen in, also when keys collide, where the smaller key wins.
+/// Like a [`TranspositionTable`], it trusts the key to tell states apart, here except for its low
+/// bits.
+#[derive(Debug)]
+pub struct SharedTranspositionTable {
+    entries: Vec<AtomicU64>,
+    mask: usize,
+}
+
+impl SharedTranspositionTable {
+    const VALUE_BITS: u32 = u16::BITS;
+    const EMPTY: u64 = u64::MAX;
+
+    /// Creates a table with room for `size` entries, rounded up to a power of two.
+    pub fn new(size: usize) -> Self {
+        let size = size.max(1).next_power_of_two();
+
+        Self {
+            entries: (0..size).map(|_| AtomicU64::new(Self::EMPTY)).collect(),
+            mask: size - 1,
+        }
+    }
+
+    fn slot(&self, key: u64) -> &AtomicU64 {
+        &self.entries[(key >> Self::VALUE_BITS) as usize & self.mask]
+    }
+
+    pub fn get(&self, key: u64) -> Option<u16> {
+        let entry = self.slot(key).load(Ordering::Relaxed);
+
+        (entry != Self::EMPTY && entry >> Self::VALUE_BITS == key >> Self::VALUE_BITS)
+            .then_some(entry as u16)
+    }
+
+    pub fn insert(&self, key: u64, value: u16) {
+        let entry = key >> Self::VALUE_BITS << Self::VALUE_BITS | value as u64;
+        self.slot(key).fetch_min(entry, Ordering::Relaxed);
+    }
+
+    pub fn clear(&mut self) {
+        for entry in self.entries.iter_mut() {
+            *entry.get_mut() = Self::EMPTY;
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SharedTranspositionTable, TranspositionTable};
+
+    #[test]
+    fn replaces_on_collision() {
+        let mut table = TranspositionTable::new(3);
+        assert_eq!(table.capacity(), 4);
+
+        table.insert(1, 'a');
+        assert_eq!(table.get(1), Some('a'));
+        assert_eq!(table.get(5), None);
+
+        table.insert(5, 'b');
+        assert_eq!(table.get(1), None);
+        assert_eq!(table.get(5), Some('b'));
+    }
+
+    #[test]
+    fn shared() {
+        let table = SharedTranspositionTable::new(4);
+        let key = |high: u64| high << 16 | 0xBEEF;
+
+        table.insert(key(6), 42);
+        table.insert(key(6), 50);
+        assert_eq!(table.get(key(6)), Some(42));
+        assert_eq!(table.get(key(2)), None);
+
+        // the smaller key keeps the slot, whichever was inserted first
+        table.insert(key(2), 7);
+        assert_eq!(table.get(key(6)), None);
+        assert_eq!(table.get(key(2)), Some(7));
+        table.insert(key(6), 0);
+        assert_eq!(table.get(key(2)), Some(7));
+    }
+}
@@ -0,0 +1,272 @@
+//! Tunes the evaluator weights with the cross-entropy method: every generation samples weight
+//! vectors from a normal distribution, lets a greedy bot play with each of them, and refits the
+//! distribution to the best performing ones.
+
+use std::{array, f64::consts::TAU, fmt::Write as _, fs, io, path::Path};
+
+use queue::Queue;
+use rand::Rng;
+
+use crate::{
+    brute_forcing::scored_moves,
+    heuristic::{Evaluator, Weights},
+    GameState,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TuningConfig {
+    /// Number of weight vectors sampled every generation.
+    pub population: usize,
+    /// Number of the best samples the next distribution is fitted to.
+    pub elite: usize,
+    /// Games every sample plays, as the queue makes a single game noisy.
+    /// The seeds of the games are drawn anew every generation.
+    pub games: usize,
+    /// Pieces after which a game is stopped.
+    pub max_pieces: usize,
+    /// Added to the deviation after every generation so that it does not collapse too early.
+    pub noise: f64,
+}
+
+impl Default for TuningConfig {
+    fn default() -> Self {
+        Self {
+            population: 32,
+            elite: 8,
+            games: 4,
+            max_pieces: 200,
+            noise: 0.05,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Population {
+    pub generation: usize,
+    pub mean: [f64; Weights::COUNT],
+    pub deviation: [f64; Weights::COUNT],
+    /// The samples of the last generation along with their fitness, best first.
+    pub members: Vec<(Weights, f64)>,
+}
+
+impl Population {
+    /// Starts the search around `weights`.
+    pub fn new(weights: Weights) -> Self {
+        Self {
+            generation: 0,
+            mean: weights.to_array(),
+            deviation: [1.0; Weights::COUNT],
+            members: Vec::new(),
+        }
+    }
+
+    pub fn best(&self) -> Option<&(Weights, f64)> {
+        self.members.first()
+    }
+
+    pub fn evolve<const W: usize, const H: usize, const D: usize>(
+        &mut self,
+        config: &TuningConfig,
+        rng: &mut impl Rng,
+    ) {
+        // every sample plays the same games, so that they are compared on equal terms
+        let seeds: Vec<u64> = (0..config.games).map(|_| rng.gen()).collect();
+        let mut members: Vec<_> = (0..config.population)
+            .map(|_| {
+                let weights = Weights::from_array(array::from_fn(|i| {
+                    self.mean[i] + self.deviation[i] * gaussian(rng)
+                }));
+                (weights, fitness::<W, H, D>(&weights, config, &seeds))
+            })
+            .collect();
+        members.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+        let elite: Vec<_> = members
+            .iter()
+            .take(config.elite.max(1))
+            .map(|(weights, _)| weights.to_array())
+            .collect();
+        if !elite.is_empty() {
+            let count = elite.len() as f64;
+            for i in 0..Weights::COUNT {
+                let mean = elite.iter().map(|weights| weights[i]).sum::<f64>() / count;
+                let variance = elite
+                    .iter()
+                    .map(|weights| (weights[i] - mean).powi(2))
+                    .sum::<f64>()
+                    / count;
+
+                self.mean[i] = mean;
+                self.deviation[i] = variance.sqrt() + config.noise;
+            }
+        }
+
+        self.members = members;
+        self.generation += 1;
+    }
+
+    /// Writes the population as text, one `generation`, `mean`, `deviation` or `member` line at a
+    /// time, where a member line holds the fitness followed by the weights. The text is written
+    /// next to `path` and then moved over it, so that a save that is interrupted leaves the file
+    /// as it was.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut text = format!("generation {}\n", self.generation);
+        let mut line = |name: &str, values: &[f64]| {
+            text.push_str(name);
+            for value in values {
+                let _ = write!(text, " {value}");
+            }
+            text.push('\n');
+        };
+
+        line("mean", &self.mean);
+        line("deviation", &self.deviation);
+        for (weights, fitness) in self.members.iter() {
+            let mut values = vec![*fitness];
+            values.extend(weights.to_array());
+            line("member", &values);
+        }
+
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        fs::write(&temporary, text)?;
+        fs::rename(&temporary, path)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+
+        let mut population = Self::new(Weights::default());
+        for (number, line) in fs::read_to_string(path)?.lines().enumerate() {
+            let mut tokens = line.split_whitespace();
+            let Some(name) = tokens.next() else {
+                continue;
+            };
+            let values = tokens
+                .map(str::parse)
+                .collect::<Result<Vec<f64>, _>>()
+                .map_err(|error| invalid(format!("line {}: {error}", number + 1)))?;
+            let weights = |values: &[f64]| {
+                <[f64; Weights::COUNT]>::try_from(values).map_err(|_| {
+                    invalid(format!(
+                        "line {}: expected {} weights",
+                        number + 1,
+                        Weights::COUNT
+                    ))
+                })
+            };
+
+            match (name, values.as_slice()) {
+                ("generation", &[generation]) => population.generation = generation as usize,
+                ("mean", values) => population.mean = weights(values)?,
+                ("deviation", values) => population.deviation = weights(values)?,
+                ("member", [fitness, values @ ..]) => population
+                    .members
+                    .push((Weights::from_array(weights(values)?), *fitness)),
+                _ => return Err(invalid(format!("line {}: unexpected `{line}`", number + 1))),
+            }
+        }
+
+        Ok(population)
+    }
+}
+
+/// Evolves `population` for `generations` more generations, saving it to `checkpoint` after
+/// each of them so that an interrupted run can resume from [`Population::load`]. The weights tried
+/// and the games played are drawn from `rng`, so a seeded generator makes a run reproducible.
+pub fn tune<const W: usize, const H: usize, const D: usize>(
+    population: &mut Population,
+    config: &TuningConfig,
+    generations: usize,
+    checkpoint: Option<&Path>,
+    rng: &mut impl Rng,
+) -> io::Result<()> {
+    for _ in 0..generations {
+        population.evolve::<W, H, D>(config, rng);
+        if let Some(path) = checkpoint {
+            population.save(path)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Plays greedily, always taking the best scored placement, until the game is over,
+/// every placement would top out or `max_pieces` pieces have been placed.
+pub fn play<E: Evaluator<W, H, D> + ?Sized, const W: usize, const H: usize, const D: usize>(
+    mut game_state: GameState<W, H, D>,
+    evaluator: &E,
+    max_pieces: usize,
+) -> GameState<W, H, D> {
+    while game_state.outcome().is_running() && game_state.stats().pieces_placed < max_pieces {
+        match scored_moves(&mut game_state, evaluator).pop() {
+            Some((_, actions)) => game_state.make(&actions),
+            None => break,
+        }
+    }
+
+    game_state
+}
+
+/// The pieces placed plus the layers cleared, averaged over one game per seed.
+pub fn fitness<const W: usize, const H: usize, const D: usize>(
+    weights: &Weights,
+    config: &TuningConfig,
+    seeds: &[u64],
+) -> f64 {
+    let total: usize = seeds
+        .iter()
+        .map(|&seed| {
+            let game_state = play(
+                GameState::<W, H, D>::from(Queue::default().with_seed(seed)),
+                weights,
+                config.max_pieces,
+            );
+            game_state.stats().pieces_placed + game_state.stats().layers_cleared
+        })
+        .sum();
+
+    total as f64 / seeds.len().max(1) as f64
+}
+
+/// Samples the standard normal distribution with the Box-Muller transform.
+fn gaussian(rng: &mut impl Rng) -> f64 {
+    let u: f64 = rng.gen_range(f64::EPSILON..1.0);
+    let v: f64 = rng.gen();
+    (-2.0 * u.ln()).sqrt() * (TAU * v).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn checkpoint() {
+        let config = TuningConfig {
+            population: 3,
+            elite: 2,
+            games: 1,
+            max_pieces: 2,
+            ..Default::default()
+        };
+        let path = std::env::temp_dir().join(format!("ternix-tuning-{}", std::process::id()));
+
+        let mut population = Population::new(Weights::default());
+        let mut rng = StdRng::seed_from_u64(7);
+        tune::<4, 8, 4>(&mut population, &config, 1, Some(&path), &mut rng).unwrap();
+        assert_eq!(population.generation, 1);
+        assert_eq!(population.members.len(), 3);
+
+        let loaded = Population::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, population);
+
+        // the same seed tunes the same way
+        let mut again = Population::new(Weights::default());
+        let mut rng = StdRng::seed_from_u64(7);
+        tune::<4, 8, 4>(&mut again, &config, 1, None, &mut rng).unwrap();
+        assert_eq!(again, population);
+    }
+}
@@ -0,0 +1,298 @@
+use std::mem;
+
+use layer::Layer;
+use piece::Piece;
+use queue::{Checkpoint, SavedMode};
+
+use crate::{stats::Streak, zobrist, Action, GameOutcome, GameState, Spin};
+
+/// What a performed action changed, so that it can be undone.
+#[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)] // boxing spawns would allocate on every lock
+pub(crate) enum Delta<const W: usize, const D: usize> {
+    /// The piece moved or rotated.
+    Move {
+        piece: Piece,
+        last_kick: Option<usize>,
+    },
+    /// The piece was held or locked, which replaces it and advances the queue.
+    Spawn(Spawn<W, D>),
+}
+
+/// What holding or locking the piece changed. Everything else, like the stats that only count
+/// up and the zobrist keys, is worked out again when undoing it.
+#[derive(Debug, Clone)]
+pub(crate) struct Spawn<const W: usize, const D: usize> {
+    piece: Piece,
+    last_kick: Option<usize>,
+    queue: Checkpoint<SavedMode>,
+    last_clear: usize,
+    last_spin: Spin,
+    outcome: GameOutcome,
+    streak: Streak,
+    /// The piece where it locked, unless it was held instead.
+    pub placed: Option<Piece>,
+    /// The layers the lock cleared, along with the index each of them had, from the bottom up.
+    pub cleared: Vec<(usize, Layer<W, D>)>,
+}
+
+impl<const W: usize, const H: usize, const D: usize> GameState<W, H, D> {
+    /// Everything that holding or locking the piece changes but cannot work out again,
+    /// before it does.
+    pub(crate) fn spawn_delta(&self) -> Spawn<W, D> {
+        Spawn {
+            piece: self.piece,
+            last_kick: self.last_kick,
+            queue: self.queue.checkpoint(),
+            last_clear: self.last_clear,
+            last_spin: self.last_spin,
+            outcome: self.outcome,
+            streak: self.stats.streak(),
+            placed: None,
+            cleared: Vec::new(),
+        }
+    }
+
+    /// Keeps at most `limit` actions to undo, forgetting the oldest ones beyond it.
+    pub fn with_undo_limit(mut self, limit: usize) -> Self {
+        self.undo_limit = limit;
+        while self.undo.len() > limit {
+            self.undo.pop_front();
+        }
+        self
+    }
+
+    /// Forgets every action there is to undo or redo, keeping the ones to come.
+    pub fn clear_undo(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    /// A copy of the state without its actions to undo or redo, to keep around cheaply.
+    pub(crate) fn snapshot(&self) -> Self {
+        let mut snapshot = self.clone();
+        snapshot.clear_undo();
+        snapshot
+    }
+
+    /// Runs `f` with every action it performs recorded regardless of the undo limit,
+    /// so that they can be taken back even if the state keeps no actions to undo otherwise.
+    pub(crate) fn unlimited<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        let limit = mem::replace(&mut self.undo_limit, usize::MAX);
+        let result = f(self);
+        self.undo_limit = limit;
+        result
+    }
+
+    /// Runs `f`, keeping what could be redone before it, so that moves it makes and unmakes
+    /// do not disturb the actions of the caller.
+    pub(crate) fn keeping_redo<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        let redo = mem::take(&mut self.redo);
+        let result = f(self);
+        self.redo = redo;
+        result
+    }
+
+    /// Records the delta of the action that was just pushed onto the history.
+    /// Doing something new discards what could have been redone.
+    pub(crate) fn record(&mut self, delta: Delta<W, D>) {
+        self.redo.clear();
+        if self.undo_limit == 0 {
+            return;
+        }
+        // moves made regardless of the limit and never unmade leave the stack above it
+        while self.undo.len() >= self.undo_limit {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(delta);
+    }
+
+    /// Takes back the most recent action, restoring the state exactly as it was before it.
+    pub fn undo(&mut self) -> Option<Action> {
+        let action = self.take_back()?;
+        self.redo.push(action);
+        Some(action)
+    }
+
+    /// Undoes the most recent action without offering it to redo.
+    pub(crate) fn take_back(&mut self) -> Option<Action> {
+        let delta = self.undo.pop_back()?;
+        let action = self.history.pop().expect("every delta has its action");
+
+        match delta {
+            Delta::Move { piece, last_kick } => {
+                self.piece = piece;
+                self.last_kick = last_kick;
+            }
+            Delta::Spawn(spawn) => {
+                let Spawn {
+                    piece,
+                    last_kick,
+                    queue,
+                    last_clear,
+                    last_spin,
+                    outcome,
+                    streak,
+                    placed,
+                    cleared,
+                } = spawn;
+
+                if let Some(placed) = placed {
+                    self.stats
+                        .unrecord(self.last_clear, self.is_empty(), self.last_spin, streak);
+                    self.restore_layers(cleared);
+                    self.remove_piece(&placed);
+                }
+
+                self.piece = piece;
+                self.last_kick = last_kick;
+                self.queue.restore(queue);
+                self.queue_key = zobrist::queue(&self.queue);
+                self.last_clear = last_clear;
+                self.last_spin = last_spin;
+                self.outcome = outcome;
+            }
+        }
+
+        Some(action)
+    }
+
+    /// Performs the most recently undone action again.
+    pub fn redo(&mut self) -> Option<Action> {
+        let action = self.redo.pop()?;
+
+        let redo = mem::take(&mut self.redo);
+        self.perform(action, true);
+        self.redo = redo;
+
+        Some(action)
+    }
+
+    /// Puts the cleared layers back where they were, moving the ones above them up again.
+    fn restore_layers(&mut self, cleared: Vec<(usize, Layer<W, D>)>) {
+        let Some(&(first, _)) = cleared.first() else {
+            return;
+        };
+        self.board_key ^= zobrist::layers(&self.playfield[first..], first);
+        for (index, layer) in cleared {
+            self.playfield[index..].rotate_right(1);
+            self.playfield[index] = layer;
+        }
+        self.board_key ^= zobrist::layers(&self.playfield[first..], first);
+    }
+
+    fn remove_piece(&mut self, piece: &Piece) {
+        let [x, y, z] = piece.position;
+        let shape = piece.shape();
+        let [_, start_y, _] = shape.bounding_box.start;
+        let [_, end_y, _] = shape.bounding_box.end;
+
+        let variant = piece.shape_id.variant;
+        for (i, &bitboard) in shape
+            .bitboards
+            .iter()
+            .enumerate()
+            .take(end_y + 1)
+            .skip(start_y)
+        {
+            let layer_y = (y + i as isize) as usize;
+            let cells = Self::translate(bitboard, x, z);
+            self.playfield[layer_y].bitboards[variant as usize] &= !cells;
+            self.board_key ^= zobrist::bitboard_key(cells, layer_y, variant);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use orientation::{Axis, Direction, Polarity};
+    use queue::{Parsing, Queue};
+    use tetrominos::Tetromino;
+
+    use super::*;
+
+    #[test]
+    fn undo_redo() {
+        // a stack with a hole the I piece completes two layers through
+        let mut playfield = [Layer::default(); 8];
+        let full = 0x0F0F_0F0F;
+        let column = 1 << (2 + 2 * layer::MAX_SIZE);
+        for (y, layer) in playfield.iter_mut().enumerate().take(3) {
+            let gaps = if y == 1 { column | 1 } else { column };
+            layer.bitboards[Tetromino::D as usize] = full & !gaps;
+        }
+        let mut game_state =
+            GameState::<4, 8, 4>::from(Queue::parse("IOTL").unwrap()).with_playfield(playfield);
+
+        let direction = Direction::from((Polarity::Positive, Axis::X));
+        let actions = [
+            Action::Hold,
+            Action::MoveLeft,
+            Action::Rotate { direction },
+            Action::HardDrop,
+            Action::Hold,
+            Action::HardDrop,
+            Action::MoveRight,
+            Action::HardDrop,
+        ];
+
+        let mut states = vec![game_state.clone()];
+        for action in actions {
+            if game_state.perform(action, true) {
+                states.push(game_state.clone());
+            }
+        }
+        assert_eq!(states.len(), actions.len() + 1);
+        assert_eq!(game_state.cleared_layers(), 2);
+
+        let same = |a: &GameState<4, 8, 4>, b: &GameState<4, 8, 4>| {
+            assert_eq!(a, b);
+            assert_eq!(a.piece, b.piece);
+            assert_eq!(a.stats, b.stats);
+            assert_eq!(a.zobrist(), b.zobrist());
+            assert_eq!(a.history, b.history);
+        };
+
+        for expected in states.iter().rev().skip(1) {
+            assert!(game_state.undo().is_some());
+            same(&game_state, expected);
+        }
+        assert_eq!(game_state.undo(), None);
+
+        for expected in states.iter().skip(1) {
+            assert!(game_state.redo().is_some());
+            same(&game_state, expected);
+        }
+        assert_eq!(game_state.redo(), None);
+
+        // doing something else discards the undone actions
+        game_state.undo();
+        assert!(game_state.perform(Action::MoveLeft, true));
+        assert_eq!(game_state.redo(), None);
+
+        // only the most recent actions are kept once the stack is capped
+        let mut capped = states[0].clone().with_undo_limit(3);
+        for action in actions {
+            capped.perform(action, true);
+        }
+        for expected in states.iter().rev().skip(1).take(3) {
+            assert!(capped.undo().is_some());
+            same(&capped, expected);
+        }
+        assert_eq!(capped.undo(), None);
+
+        // a move made regardless of the limit is trimmed back to it by the next action
+        let mut capped = states[0].clone().with_undo_limit(2);
+        let moves = capped.moves();
+        let longest = moves.iter().max_by_key(|actions| actions.len()).unwrap();
+        assert!(longest.len() > 2);
+        capped.make(longest);
+        assert!(capped.perform(Action::SoftDrop, true));
+        let undone = std::iter::from_fn(|| capped.undo()).count();
+        assert_eq!(undone, 2);
+
+        capped.redo();
+        capped.clear_undo();
+        assert_eq!((capped.undo(), capped.redo()), (None, None));
+    }
+}
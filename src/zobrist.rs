@@ -0,0 +1,144 @@
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    mem,
+};
+
+use layer::Layer;
+use piece::Piece;
+use queue::{Queue, RandomizerMode};
+use strum::{EnumCount, IntoEnumIterator};
+use tetrominos::Tetromino;
+
+use crate::{GameOutcome, Spin, Stats};
+
+const CELL: u64 = 0x243F_6A88_85A3_08D3;
+const PIECE: u64 = 0x1319_8A2E_0370_7344;
+const HOLD: u64 = 0xA409_3822_299F_31D0;
+const PREVIEW: u64 = 0x082E_FA98_EC4E_6C89;
+const STATS: u64 = 0x4528_21E6_38D0_1377;
+
+/// A pseudo random key for `feature`. Keys are derived on demand instead of looked up in a table,
+/// so that they exist for playfields of any size.
+fn key(feature: u64) -> u64 {
+    // splitmix64
+    let mut z = feature.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+pub fn cell(y: usize, index: usize, variant: Tetromino) -> u64 {
+    let cells_per_layer = layer::MAX_SIZE * layer::MAX_SIZE;
+    let feature = (y * cells_per_layer + index) * Tetromino::COUNT + variant as usize;
+    key(CELL ^ feature as u64)
+}
+
+/// The combined key of every occupied cell of `layers`, the first of which is at height `first`.
+pub fn layers<const W: usize, const D: usize>(layers: &[Layer<W, D>], first: usize) -> u64 {
+    let mut result = 0;
+
+    for (y, layer) in layers.iter().enumerate() {
+        for (variant, &bitboard) in Tetromino::iter().zip(layer.bitboards.iter()) {
+            result ^= bitboard_key(bitboard, first + y, variant);
+        }
+    }
+
+    result
+}
+
+/// The combined key of the cells of `bitboard` at height `y`.
+pub fn bitboard_key(mut bitboard: u64, y: usize, variant: Tetromino) -> u64 {
+    let mut result = 0;
+
+    while bitboard != 0 {
+        result ^= cell(y, bitboard.trailing_zeros() as usize, variant);
+        bitboard &= bitboard - 1;
+    }
+
+    result
+}
+
+pub fn piece(piece: &Piece) -> u64 {
+    let Piece {
+        shape_id,
+        position: [x, y, z],
+    } = piece.canonical();
+
+    let mut result = key(PIECE ^ shape_id.combination() as u64);
+    for component in [x, y, z] {
+        result = key(result ^ component as u64);
+    }
+    result
+}
+
+/// The key of how a game has scored so far and how its most recent drop went. It leaves out the
+/// score table, which stays the same for the whole game.
+pub fn stats(stats: &Stats, outcome: GameOutcome, last_clear: usize, last_spin: Spin) -> u64 {
+    let Stats {
+        table: _,
+        pieces_placed,
+        layers_cleared,
+        combo,
+        back_to_back,
+        perfect_clears,
+        spins,
+        score,
+    } = *stats;
+
+    [
+        pieces_placed as u64,
+        layers_cleared as u64,
+        combo as u64,
+        back_to_back as u64,
+        perfect_clears as u64,
+        spins as u64,
+        score,
+        outcome as u64,
+        last_clear as u64,
+        last_spin as u64,
+    ]
+    .into_iter()
+    .fold(STATS, |result, component| key(result ^ component))
+}
+
+/// The combined key of the parts of `queue` that drawing and holding change: the hold slot, the
+/// preview, the random number generator and the front of the randomizer. A sequence of patterns
+/// only counts the pattern it draws from next and its length, which costs the same however long
+/// it is and still tells apart every queue the same queue can turn into, as the patterns behind
+/// the current one are drawn in order and left untouched until then.
+pub fn queue(queue: &Queue) -> u64 {
+    let hold = queue
+        .hold
+        .map_or(Tetromino::COUNT, |variant| variant as usize);
+    let mut result = key(HOLD ^ (hold * 2 + queue.can_swap as usize) as u64);
+
+    for (index, &variant) in queue.preview.iter().enumerate() {
+        result ^= key(PREVIEW ^ (index * Tetromino::COUNT + variant as usize) as u64);
+    }
+
+    let randomizer = &queue.randomizer;
+    let discriminant = mem::discriminant(randomizer);
+    result ^= match randomizer {
+        RandomizerMode::Patterns(patterns) => {
+            let current = patterns.current();
+            hashed((
+                discriminant,
+                patterns.sequence.len(),
+                current.map(|index| (index, &patterns.sequence[index])),
+                patterns.endless,
+            ))
+        }
+        RandomizerMode::Cyclic(cyclic) => {
+            hashed((discriminant, cyclic.cycle.len(), cyclic.position))
+        }
+        _ => hashed(randomizer),
+    };
+
+    result ^ hashed(&queue.rng)
+}
+
+fn hashed<T: Hash>(value: T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}